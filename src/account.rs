@@ -0,0 +1,189 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::analyzer::{analyze, Analyzer};
+use crate::model::{Chat, ChatType, Id};
+use crate::{ChatStatistics, Options};
+
+/// Either a single-chat export or a whole account produced by "Export all data".
+#[derive(Debug)]
+pub enum Export<'a> {
+    /// A single chat, exported from its menu.
+    Chat(Chat<'a>),
+    /// Every chat of the account.
+    Account(Account<'a>),
+}
+
+/// What "Export all data" wrote, besides the chats left out by the filter.
+#[derive(Debug, Serialize)]
+pub struct Account<'a> {
+    /// Only present if it was exported.
+    pub personal_information: Option<PersonalInformation>,
+    /// Only present if it was exported.
+    pub contacts: Option<Contacts>,
+    /// `chats.list` of the export.
+    pub chats: Vec<Chat<'a>>,
+    /// `left_chats.list` of the export.
+    pub left_chats: Vec<Chat<'a>>,
+}
+
+/// The `personal_information` of an account export, as Telegram writes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalInformation {
+    /// The account's own user id.
+    pub user_id: Option<u64>,
+    /// As set in the profile.
+    pub first_name: Option<String>,
+    /// As set in the profile.
+    pub last_name: Option<String>,
+    /// As set in the profile.
+    pub phone_number: Option<String>,
+    /// Without the `@`.
+    pub username: Option<String>,
+    /// As set in the profile.
+    pub bio: Option<String>,
+}
+
+/// The `contacts` of an account export.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Contacts {
+    /// Telegram's description of the section.
+    #[serde(default)]
+    pub about: Option<String>,
+    /// The contacts themselves.
+    #[serde(default)]
+    pub list: Vec<Contact>,
+}
+
+/// A contact of the account, as Telegram writes it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Contact {
+    /// As saved in the contact.
+    pub first_name: Option<String>,
+    /// As saved in the contact.
+    pub last_name: Option<String>,
+    /// As saved in the contact.
+    pub phone_number: Option<String>,
+    /// When the contact was added.
+    pub date: Option<NaiveDateTime>,
+}
+
+/// Selects chats of an account export by name, `Id` or `ChatType`.
+///
+/// Each non-empty criterion must match; an empty filter selects every chat.
+#[derive(Debug, Default)]
+pub struct ChatFilter {
+    /// Exact chat names.
+    pub names: Vec<String>,
+    /// The `id`s of the chats.
+    pub ids: Vec<u128>,
+    /// Chat types such as `personal_chat` or `private_supergroup`.
+    pub types: Vec<ChatType>,
+}
+
+impl ChatFilter {
+    /// Whether the chat is selected.
+    pub fn matches(&self, name: &str, chat_type: &ChatType, id: &Id) -> bool {
+        (self.names.is_empty() || self.names.iter().any(|n| n == name))
+            && (self.ids.is_empty() || self.ids.contains(&id.0))
+            && (self.types.is_empty() || self.types.contains(chat_type))
+    }
+}
+
+/// What an `Analyzer` gathered from either kind of export.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ExportStatistics<A> {
+    /// Of a single-chat export.
+    Chat(A),
+    /// Of every chat of an account export, and of all of them together.
+    Account(AccountStatistics<A>),
+}
+
+impl ExportStatistics<ChatStatistics<'_>> {
+    /// Ranks the `n` most used tokens of every chat and of the account total.
+    pub fn rank(&mut self, n: usize) {
+        match self {
+            ExportStatistics::Chat(stat) => stat.rank(n),
+            ExportStatistics::Account(account) => {
+                for report in &mut account.chats {
+                    report.statistics.rank(n);
+                }
+                account.total.rank(n);
+            }
+        }
+    }
+    /// Summarizes the members of every chat and of the account total.
+    pub fn summarize(&mut self) {
+        match self {
+            ExportStatistics::Chat(stat) => stat.summarize(),
+            ExportStatistics::Account(account) => {
+                for report in &mut account.chats {
+                    report.statistics.summarize();
+                }
+                account.total.summarize();
+            }
+        }
+    }
+}
+
+/// The statistics of every chat of an account export.
+#[derive(Debug, Serialize)]
+pub struct AccountStatistics<A> {
+    /// Whose account it is.
+    pub personal_information: Option<PersonalInformation>,
+    /// Chats and left chats, in the order of the export.
+    pub chats: Vec<ChatReport<A>>,
+    /// Every chat merged.
+    pub total: A,
+}
+
+/// The statistics of one chat of an account export, with what tells it apart.
+#[derive(Debug, Serialize)]
+pub struct ChatReport<A> {
+    /// Empty for chats of deleted accounts.
+    pub name: String,
+    /// Personal chat, group, channel and so on.
+    #[serde(rename = "type")]
+    pub chat_type: ChatType,
+    /// The `id` of the chat.
+    pub id: Id,
+    /// Whether it comes from `left_chats`.
+    pub left: bool,
+    /// What was gathered from its messages.
+    pub statistics: A,
+}
+
+impl<A: Clone> AccountStatistics<A> {
+    /// Runs `A` over every chat of `account`.
+    pub fn gather<'a>(account: &'a Account, options: &Options) -> Self
+    where
+        A: Analyzer<'a>,
+    {
+        let lists = [(&account.chats, false), (&account.left_chats, true)];
+        let reports = lists
+            .into_iter()
+            .flat_map(|(chats, left)| chats.iter().map(move |chat| (chat, left)))
+            .map(|(chat, left)| ChatReport {
+                name: chat.name.to_string(),
+                chat_type: chat.chat_type.clone(),
+                id: chat.id.clone(),
+                left,
+                statistics: analyze(&chat.messages, options),
+            })
+            .collect();
+        Self::from_reports(account.personal_information.clone(), reports)
+    }
+    /// Adds up the `total` of `chats`, which have been finished already.
+    pub fn from_reports<'a>(personal_information: Option<PersonalInformation>, chats: Vec<ChatReport<A>>) -> Self
+    where
+        A: Analyzer<'a>,
+    {
+        let mut total = A::default();
+        for report in &chats {
+            total.merge(report.statistics.clone());
+        }
+        total.finish();
+        Self { personal_information, chats, total }
+    }
+}
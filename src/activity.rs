@@ -0,0 +1,298 @@
+use chrono::{Datelike, NaiveDateTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::member::{Member, Members};
+use crate::model::{Message, MessageType};
+use crate::tokens::TokenStatistics;
+
+/// What every member did, besides the words they used.
+#[derive(Debug, Default, Clone)]
+pub struct Activity<'a> {
+    /// The activity of every member.
+    pub members: HashMap<Member<'a>, MemberActivity>,
+    /// Author of every message, to tell whom replies went to, as an index into `interned`.
+    ///
+    /// The only part that grows with the export rather than the statistics, hence kept to an id and an index.
+    authors: HashMap<u64, u32>,
+    /// Every author once, with their index.
+    interned: Vec<Member<'a>>,
+    indices: HashMap<Member<'a>, u32>,
+    /// Replies by the id they reply to, until `finish` credits them.
+    replies: HashMap<u64, usize>,
+}
+
+/// What a member did, gathered message by message; service messages aside.
+#[derive(Debug, Default, Clone)]
+pub struct MemberActivity {
+    /// Messages sent.
+    pub messages: usize,
+    /// Messages by their length in characters.
+    pub lengths: BTreeMap<usize, usize>,
+    /// When the earliest message was sent, `None` until one is.
+    pub first: Option<NaiveDateTime>,
+    /// When the latest message was sent.
+    pub last: Option<NaiveDateTime>,
+    /// Messages by hour of the day.
+    pub hours: [usize; 24],
+    /// From Monday.
+    pub weekdays: [usize; 7],
+    /// Messages that reply to another one.
+    pub replies_sent: usize,
+    /// Replies to the member's messages, only known once `Activity` is finished.
+    pub replies_received: usize,
+    /// Messages edited after they were sent.
+    pub edits: usize,
+    /// Messages forwarded from elsewhere.
+    pub forwards: usize,
+    /// Messages with a photo, a file or other media.
+    pub media: usize,
+}
+
+impl<'a> Analyzer<'a> for Activity<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        let message = observation.message;
+        let (MessageType::Message, Some(from)) = (&message.message_type, &observation.from) else {
+            return;
+        };
+        let author = self.intern(from);
+        self.authors.insert(message.id, author);
+        // Replies to another chat have a `reply_to_peer_id`, the id is meaningless here.
+        if let (Some(target), None) = (message.reply_to_message_id, &message.reply_to_peer_id) {
+            *self.replies.entry(target).or_insert(0) += 1;
+        }
+        self.members.entry(from.clone()).or_default().observe(message, observation.date);
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (member, activity) in other.members {
+            self.members.entry(member).or_default().merge(activity);
+        }
+        let indices: Vec<u32> = other.interned.iter().map(|member| self.intern(member)).collect();
+        self.authors.extend(other.authors.into_iter().map(|(id, author)| (id, indices[author as usize])));
+        for (target, replies) in other.replies {
+            *self.replies.entry(target).or_insert(0) += replies;
+        }
+    }
+
+    /// Credits replies to their authors; replies to messages missing from the export are dropped.
+    ///
+    /// Message ids only mean something within a chat, so the authors are let go rather than matched against later merges.
+    fn finish(&mut self) {
+        for (target, replies) in self.replies.drain() {
+            if let Some(activity) = self.authors.get(&target).and_then(|&author| self.members.get_mut(&self.interned[author as usize])) {
+                activity.replies_received += replies;
+            }
+        }
+        self.authors = HashMap::new();
+        self.interned = Vec::new();
+        self.indices = HashMap::new();
+    }
+}
+
+impl<'a> Activity<'a> {
+    fn intern(&mut self, member: &Member<'a>) -> u32 {
+        if let Some(&index) = self.indices.get(member) {
+            return index;
+        }
+        let index = self.interned.len() as u32;
+        self.interned.push(member.clone());
+        self.indices.insert(member.clone(), index);
+        index
+    }
+
+    /// Detaches the activity from the messages it was gathered from.
+    pub fn into_owned(self) -> Activity<'static> {
+        Activity {
+            members: self.members.into_iter().map(|(member, activity)| (member.into_owned(), activity)).collect(),
+            authors: self.authors,
+            interned: self.interned.into_iter().map(Member::into_owned).collect(),
+            indices: self.indices.into_iter().map(|(member, index)| (member.into_owned(), index)).collect(),
+            replies: self.replies,
+        }
+    }
+}
+
+impl MemberActivity {
+    fn observe(&mut self, message: &Message, date: NaiveDateTime) {
+        self.messages += 1;
+        let length = message.text_entities.iter().map(|entity| entity.text.chars().count()).sum();
+        *self.lengths.entry(length).or_insert(0) += 1;
+        self.first = Some(self.first.map_or(date, |first| first.min(date)));
+        self.last = Some(self.last.map_or(date, |last| last.max(date)));
+        self.hours[date.hour() as usize] += 1;
+        self.weekdays[date.weekday().num_days_from_monday() as usize] += 1;
+        self.replies_sent += usize::from(message.reply_to_message_id.is_some());
+        self.edits += usize::from(message.edited.is_some());
+        self.forwards += usize::from(message.forwarded_from.is_some());
+        self.media += usize::from(message.media_type.is_some() || message.photo.is_some() || message.file.is_some());
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.messages += other.messages;
+        for (length, messages) in other.lengths {
+            *self.lengths.entry(length).or_insert(0) += messages;
+        }
+        self.first = match (self.first, other.first) {
+            (Some(first), Some(other)) => Some(first.min(other)),
+            (first, other) => first.or(other),
+        };
+        // `None` is less than any date.
+        self.last = self.last.max(other.last);
+        for (hour, messages) in other.hours.into_iter().enumerate() {
+            self.hours[hour] += messages;
+        }
+        for (weekday, messages) in other.weekdays.into_iter().enumerate() {
+            self.weekdays[weekday] += messages;
+        }
+        self.replies_sent += other.replies_sent;
+        self.replies_received += other.replies_received;
+        self.edits += other.edits;
+        self.forwards += other.forwards;
+        self.media += other.media;
+    }
+
+    fn median_length(&self) -> f64 {
+        // The middle message, or the two middle ones when there's an even number of them.
+        let middle = [(self.messages - 1) / 2, self.messages / 2];
+        let mut lengths = [0; 2];
+        let mut seen = 0;
+        for (&length, &messages) in &self.lengths {
+            for (i, &position) in middle.iter().enumerate() {
+                if (seen..seen + messages).contains(&position) {
+                    lengths[i] = length;
+                }
+            }
+            seen += messages;
+        }
+        (lengths[0] + lengths[1]) as f64 / 2.0
+    }
+}
+
+/// A member at a glance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberSummary {
+    /// Who it is about.
+    pub member: Member<'static>,
+    /// The name of the member's most recent message.
+    pub name: Option<String>,
+    /// Messages sent, service messages aside.
+    pub messages: usize,
+    /// `messages` over the messages of every member.
+    pub share: f64,
+    /// Tokens used, stopwords aside.
+    pub tokens: usize,
+    /// Distinct tokens.
+    pub vocabulary: usize,
+    /// In characters, like `median_length`.
+    pub average_length: f64,
+    /// In characters, the mean of the two middle messages when there's an even number of them.
+    pub median_length: f64,
+    /// When the earliest message was sent.
+    pub first_message: NaiveDateTime,
+    /// When the latest message was sent.
+    pub last_message: NaiveDateTime,
+    /// The hour of the day with the most messages, the earliest one on ties.
+    pub most_active_hour: u32,
+    /// The weekday with the most messages, the earliest one from Monday on ties.
+    pub most_active_weekday: Weekday,
+    /// Messages that reply to another one.
+    pub replies_sent: usize,
+    /// Replies to the member's messages.
+    pub replies_received: usize,
+    /// Messages edited after they were sent.
+    pub edits: usize,
+    /// Messages forwarded from elsewhere.
+    pub forwards: usize,
+    /// Messages with a photo, a file or other media.
+    pub media: usize,
+}
+
+impl MemberSummary {
+    /// Summarizes every member, the most active first.
+    pub fn all(activity: &Activity, tokens: &TokenStatistics, members: &Members) -> Vec<Self> {
+        let total: usize = activity.members.values().map(|activity| activity.messages).sum();
+        let mut summaries: Vec<Self> = activity
+            .members
+            .iter()
+            .map(|(member, activity)| {
+                let member_tokens = tokens.members_tokens_map.get(member);
+                let characters: usize = activity.lengths.iter().map(|(length, messages)| length * messages).sum();
+                MemberSummary {
+                    member: member.clone().into_owned(),
+                    name: members.0.get(member).map(|names| names.name.to_string()),
+                    messages: activity.messages,
+                    share: activity.messages as f64 / total as f64,
+                    tokens: member_tokens.map_or(0, |map| map.values().sum()),
+                    vocabulary: member_tokens.map_or(0, |map| map.len()),
+                    average_length: characters as f64 / activity.messages as f64,
+                    median_length: activity.median_length(),
+                    first_message: activity.first.unwrap_or_default(),
+                    last_message: activity.last.unwrap_or_default(),
+                    most_active_hour: busiest(&activity.hours) as u32,
+                    most_active_weekday: Weekday::try_from(busiest(&activity.weekdays) as u8).unwrap_or(Weekday::Mon),
+                    replies_sent: activity.replies_sent,
+                    replies_received: activity.replies_received,
+                    edits: activity.edits,
+                    forwards: activity.forwards,
+                    media: activity.media,
+                }
+            })
+            .collect();
+        summaries.sort_unstable_by(|a, b| b.messages.cmp(&a.messages).then_with(|| a.member.to_string().cmp(&b.member.to_string())));
+        summaries
+    }
+}
+
+/// The index with the most messages, the first one on ties.
+fn busiest(counts: &[usize]) -> usize {
+    counts.iter().enumerate().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(&a.0))).map_or(0, |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::borrow::Cow;
+
+    use crate::model::{TextEntity, TextEntityType};
+
+    fn activity(texts: &[&str]) -> MemberActivity {
+        let date = NaiveDate::from_ymd_opt(2023, 1, 12).unwrap().and_hms_opt(15, 4, 5).unwrap();
+        let mut activity = MemberActivity::default();
+        for text in texts {
+            let entity = TextEntity { text_type: TextEntityType::Plain, text: Cow::Borrowed(*text), ..Default::default() };
+            activity.observe(&Message { text_entities: vec![entity], ..Default::default() }, date);
+        }
+        activity
+    }
+
+    #[test]
+    fn median_of_an_odd_number_of_messages() {
+        assert_eq!(activity(&["a"]).median_length(), 1.0);
+        assert_eq!(activity(&["aaaaaaaaaa", "a", "aaa"]).median_length(), 3.0);
+        assert_eq!(activity(&["aa", "aa", "aa", "a", "aaaaaaaaaa"]).median_length(), 2.0);
+    }
+
+    #[test]
+    fn median_of_an_even_number_of_messages() {
+        assert_eq!(activity(&["a", "aaaa"]).median_length(), 2.5);
+        assert_eq!(activity(&["", "aa", "aaa", "aaaaaaaaaa"]).median_length(), 2.5);
+        assert_eq!(activity(&["aa", "aa", "aaaaaa", "aaaaaa"]).median_length(), 4.0);
+    }
+
+    #[test]
+    fn median_counts_characters() {
+        assert_eq!(activity(&["привіт", "👋"]).median_length(), 3.5);
+    }
+
+    #[test]
+    fn median_survives_merging() {
+        let mut merged = activity(&["a", "aaaaaaaaaa"]);
+        merged.merge(activity(&["aaa", "aaaaa"]));
+        assert_eq!(merged.median_length(), activity(&["a", "aaa", "aaaaa", "aaaaaaaaaa"]).median_length());
+        assert_eq!(merged.median_length(), 4.0);
+    }
+}
@@ -0,0 +1,375 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::member::Member;
+use crate::model::{Message, MessageType};
+use crate::{remove_emojis, Options, Token};
+
+/// A statistic gathered in a single pass over the messages of a chat.
+///
+/// The messages are split between threads, each observing its share into its own `Default`
+/// analyzer, and the results are merged pairwise in no particular order.
+pub trait Analyzer<'a>: Default + Send {
+    /// Counts one message, in no particular order.
+    fn observe(&mut self, observation: &Observation<'a, '_>);
+    /// Adds up what `other` observed, as if this analyzer had observed it too.
+    fn merge(&mut self, other: Self);
+    /// Called once every message was observed and merged.
+    ///
+    /// Account totals merge finished analyzers and are finished again, so this must not count anything twice.
+    fn finish(&mut self) {}
+}
+
+/// An analyzer that can outlive the messages it observed, for the drivers that drop messages once analyzed:
+/// `stream::gather` and `html::gather`.
+///
+/// Analyzers that own everything they keep are their own `Borrowed`:
+///
+/// ```
+/// use teleparser::schema::Schema;
+/// use teleparser::{Analyzer, ChatStatistics, Detached, ExportStatistics, Observation, Options};
+///
+/// /// Messages with a photo.
+/// #[derive(Default, Clone)]
+/// struct Photos(usize);
+///
+/// impl Analyzer<'_> for Photos {
+///     fn observe(&mut self, observation: &Observation) {
+///         self.0 += usize::from(observation.message.photo.is_some());
+///     }
+///     fn merge(&mut self, other: Self) {
+///         self.0 += other.0;
+///     }
+/// }
+///
+/// impl Detached for Photos {
+///     type Borrowed<'a> = Photos;
+///     fn detach(analyzer: Photos) -> Self {
+///         analyzer
+///     }
+/// }
+///
+/// let export = r#"{"name": "Chat", "type": "personal_chat", "id": 1, "messages": [
+///     {"id": 1, "type": "message", "date": "2023-01-01T10:00:00", "from": "Alice", "from_id": "user1", "photo": "photos/1.jpg", "text": "", "text_entities": []},
+///     {"id": 2, "type": "message", "date": "2023-01-01T10:01:00", "from": "Bob", "from_id": "user2", "text": "nice", "text_entities": [{"type": "plain", "text": "nice"}]}
+/// ]}"#;
+/// // Both analyzers in a single pass, one message at a time.
+/// let stat = teleparser::stream::gather(export.as_bytes(), 1, &Default::default(), &Schema::default(), &Options::default())?;
+/// let ExportStatistics::Chat((chat, photos)): ExportStatistics<(ChatStatistics, Photos)> = stat else {
+///     unreachable!();
+/// };
+/// assert_eq!((chat.tokens.num_tokens, photos.0), (1, 1));
+/// # Ok::<(), teleparser::Error>(())
+/// ```
+pub trait Detached: Analyzer<'static> {
+    /// The same analyzer, borrowing from the messages it observes.
+    type Borrowed<'a>: Analyzer<'a>;
+    /// Takes whatever `analyzer` borrows from the messages.
+    fn detach(analyzer: Self::Borrowed<'_>) -> Self;
+}
+
+/// Runs several analyzers in the same pass, e.g. a custom one alongside `ChatStatistics`.
+macro_rules! tuples {
+    ($($analyzer:ident $index:tt),+) => {
+        impl<'a, $($analyzer: Analyzer<'a>),+> Analyzer<'a> for ($($analyzer,)+) {
+            fn observe(&mut self, observation: &Observation<'a, '_>) {
+                $(self.$index.observe(observation);)+
+            }
+            fn merge(&mut self, other: Self) {
+                $(self.$index.merge(other.$index);)+
+            }
+            fn finish(&mut self) {
+                $(self.$index.finish();)+
+            }
+        }
+
+        impl<$($analyzer: Detached),+> Detached for ($($analyzer,)+) {
+            type Borrowed<'a> = ($($analyzer::Borrowed<'a>,)+);
+            fn detach(analyzer: Self::Borrowed<'_>) -> Self {
+                ($($analyzer::detach(analyzer.$index),)+)
+            }
+        }
+    };
+}
+
+tuples!(A 0, B 1);
+tuples!(A 0, B 1, C 2);
+tuples!(A 0, B 1, C 2, D 3);
+
+/// Runs `A` over every message `options.filter` matches.
+pub fn analyze<'a, A: Analyzer<'a>>(messages: &'a [Message<'a>], options: &Options) -> A {
+    let mut analyzer: A = fold(messages, options);
+    analyzer.finish();
+    analyzer
+}
+
+/// `analyze` without `finish`, for drivers that merge the analyzers of several runs.
+pub(crate) fn fold<'a, A: Analyzer<'a>>(messages: &'a [Message<'a>], options: &Options) -> A {
+    messages
+        .par_iter()
+        .filter(|message| options.filter.matches(message, options.zone.local(message)))
+        .fold(A::default, |mut analyzer, message| {
+            analyzer.observe(&Observation::new(message, options));
+            analyzer
+        })
+        .reduce(A::default, |mut analyzer, other| {
+            analyzer.merge(other);
+            analyzer
+        })
+}
+
+/// Which messages are analyzed, checked before any analyzer sees them.
+///
+/// Each non-empty criterion must match; an empty filter selects every message.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MessageFilter {
+    /// The first day analyzed, in `Options::zone`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<NaiveDate>,
+    /// The last day analyzed, inclusive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDate>,
+    /// Members by id, like `user123`, or display name. Service messages belong to their actor.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+    /// Members left out, like `members`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_members: Vec<String>,
+}
+
+impl MessageFilter {
+    /// Whether the filter selects every message.
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none() && self.members.is_empty() && self.exclude_members.is_empty()
+    }
+
+    /// `date` is when `message` was sent, in the zone of the analysis.
+    pub fn matches(&self, message: &Message, date: NaiveDateTime) -> bool {
+        let day = date.date();
+        self.since.is_none_or(|since| day >= since)
+            && self.until.is_none_or(|until| day <= until)
+            && (self.members.is_empty() || is_one_of(message, &self.members))
+            && !is_one_of(message, &self.exclude_members)
+    }
+}
+
+fn is_one_of(message: &Message, members: &[String]) -> bool {
+    let (member, name) = match message.message_type {
+        MessageType::Message => (Member::of(message), message.from.as_ref().map(|from| from.0.as_ref())),
+        MessageType::Service => (Member::actor(message), message.actor.as_deref()),
+    };
+    let id = member.map(|member| member.to_string());
+    members.iter().any(|wanted| Some(wanted.as_str()) == id.as_deref() || Some(wanted.as_str()) == name)
+}
+
+/// A message, tokenized once for every analyzer.
+pub struct Observation<'a, 'o> {
+    /// The message itself, service messages included.
+    pub message: &'a Message<'a>,
+    /// Channel posts signed by no one have neither `from` nor `from_id`, they only count overall.
+    pub from: Option<Member<'a>>,
+    /// When the message was sent, in `options.zone`.
+    pub date: NaiveDateTime,
+    /// One per text entity that isn't meta, service messages have none.
+    pub entities: Vec<EntityTokens<'a>>,
+    /// What the analysis was asked to count.
+    pub options: &'o Options,
+}
+
+/// The tokens of one text entity.
+pub struct EntityTokens<'a> {
+    /// The counted tokens, lemmatized or stemmed if enabled.
+    pub tokens: Vec<Token<'a>>,
+    /// What each of `tokens` was before lemmatization and stemming, empty if those are disabled.
+    pub surfaces: Vec<Token<'a>>,
+    /// Tokens left out as stopwords.
+    pub stopwords: Vec<Token<'a>>,
+    /// Indices of `tokens` that came right after a left out stopword or emoji.
+    pub gaps: Vec<usize>,
+}
+
+impl<'a> EntityTokens<'a> {
+    /// Stretches of `tokens` that were next to each other in the text, split where stopwords or emojis were left out.
+    pub fn runs(&self) -> impl Iterator<Item = &[Token<'a>]> {
+        let mut start = 0;
+        self.gaps.iter().copied().chain([self.tokens.len()]).map(move |end| {
+            let run = &self.tokens[start..end];
+            start = end;
+            run
+        })
+    }
+}
+
+impl<'a, 'o> Observation<'a, 'o> {
+    /// Tokenizes `message` the way `options` say; `analyze` does so for every message.
+    pub fn new(message: &'a Message<'a>, options: &'o Options) -> Self {
+        let mut observation = Observation { message, from: Member::of(message), date: options.zone.local(message), entities: Vec::new(), options };
+        if message.message_type == MessageType::Service {
+            return observation;
+        }
+        for entity in message.text_entities.iter().filter(|entity| !entity.text_type.is_meta()) {
+            let mut tokens = EntityTokens { tokens: Vec::new(), surfaces: Vec::new(), stopwords: Vec::new(), gaps: Vec::new() };
+            for token in options.tokenizer.tokenize(&entity.text) {
+                let token = options.normalization.apply(remove_emojis(token));
+                // Emoji-only tokens are left empty, they are no words.
+                if token.is_empty() || options.stopwords.contains(&token) {
+                    if !token.is_empty() {
+                        tokens.stopwords.push(Token(token));
+                    }
+                    if tokens.gaps.last() != Some(&tokens.tokens.len()) {
+                        tokens.gaps.push(tokens.tokens.len());
+                    }
+                } else if options.morphology.is_enabled() {
+                    tokens.tokens.push(Token(options.morphology.canonical(&token)));
+                    tokens.surfaces.push(Token(token));
+                } else {
+                    tokens.tokens.push(Token(token));
+                }
+            }
+            observation.entities.push(tokens);
+        }
+        observation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+    use std::borrow::Cow;
+
+    use crate::model::{MemberId, Person, TextEntity, TextEntityType};
+    use crate::zone::Zone;
+    use crate::ChatStatistics;
+
+    fn message(id: u64, user: u64, text: &str) -> Message<'static> {
+        let date = NaiveDate::from_ymd_opt(2023, 1, 1 + (id % 28) as u32).unwrap().and_hms_opt((id % 24) as u32, 0, 0).unwrap();
+        Message {
+            id,
+            date,
+            from: Some(Person(Cow::Owned(format!("User {user}")))),
+            from_id: Some(MemberId::User(user)),
+            reply_to_message_id: id.checked_sub(3).filter(|_| id.is_multiple_of(2)),
+            text_entities: vec![TextEntity { text_type: TextEntityType::Plain, text: Cow::Owned(text.to_string()), ..Default::default() }],
+            ..Default::default()
+        }
+    }
+
+    fn in_pool<T: Send>(threads: usize, f: impl FnOnce() -> T + Send) -> T {
+        ThreadPoolBuilder::new().num_threads(threads).build().unwrap().install(f)
+    }
+
+    fn json(mut stat: ChatStatistics) -> serde_json::Value {
+        stat.rank(10);
+        stat.summarize();
+        serde_json::to_value(stat).unwrap()
+    }
+
+    #[test]
+    fn analyzes_fewer_messages_than_threads() {
+        let options = Options { ngrams: vec![2], ..Default::default() };
+        let empty: ChatStatistics = in_pool(8, || analyze(&[], &options));
+        assert_eq!(empty.tokens.num_tokens, 0);
+        assert!(empty.tokens.tokens_map.is_empty());
+
+        let single = [message(1, 1, "a lone message")];
+        let stat: ChatStatistics = in_pool(8, || analyze(&single, &options));
+        assert_eq!(stat.tokens.num_tokens, 3);
+        assert_eq!(stat.activity.members[&Member::Id(MemberId::User(1))].messages, 1);
+        assert_eq!(json(stat), json(in_pool(1, || analyze(&single, &options))));
+    }
+
+    #[test]
+    fn results_do_not_depend_on_the_number_of_threads() {
+        let words = ["fox", "dog", "the", "quick", "lazy", "🙂", "jumps", "over"];
+        let messages: Vec<Message> = (1..=100).map(|id| {
+            let text: Vec<&str> = (0..id % 7 + 1).map(|i| words[((id * 3 + i) % 8) as usize]).collect();
+            message(id, id % 5, &text.join(" "))
+        }).collect();
+        let options = Options { ngrams: vec![2, 3], ..Default::default() };
+
+        let expected = json(in_pool(1, || analyze(&messages, &options)));
+        for threads in [2, 3, 8, 64] {
+            assert_eq!(json(in_pool(threads, || analyze(&messages, &options))), expected, "{threads} threads");
+        }
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 1, day).unwrap()
+    }
+
+    fn members(members: &[&str]) -> Vec<String> {
+        members.iter().map(|member| member.to_string()).collect()
+    }
+
+    fn service(id: u64, actor_id: Option<MemberId>, actor: &str) -> Message<'static> {
+        Message { id, message_type: MessageType::Service, date: day(1).and_hms_opt(0, 0, 0).unwrap(), actor: Some(actor.to_string()), actor_id, ..Default::default() }
+    }
+
+    #[test]
+    fn filters_days_inclusively() {
+        let filter = MessageFilter { since: Some(day(10)), until: Some(day(12)), ..Default::default() };
+        let message = message(1, 1, "hi");
+        let at = |d, h, m| day(d).and_hms_opt(h, m, 0).unwrap();
+        assert!(!filter.matches(&message, at(9, 23, 59)));
+        assert!(filter.matches(&message, at(10, 0, 0)));
+        assert!(filter.matches(&message, at(12, 23, 59)));
+        assert!(!filter.matches(&message, at(13, 0, 0)));
+        assert!(MessageFilter { since: Some(day(10)), ..Default::default() }.matches(&message, at(31, 0, 0)));
+        assert!(MessageFilter { until: Some(day(12)), ..Default::default() }.matches(&message, at(1, 0, 0)));
+    }
+
+    #[test]
+    fn filters_days_in_the_analysis_zone() {
+        // 23:30 on the 9th in the export, 00:30 on the 10th an hour east of it.
+        let mut late = message(1, 1, "late");
+        late.date = day(9).and_hms_opt(23, 30, 0).unwrap();
+        late.date_unixtime = Some(late.date.and_utc().timestamp());
+        let messages = [late];
+        let filter = MessageFilter { since: Some(day(10)), ..Default::default() };
+
+        let export = Options { filter: filter.clone(), ..Default::default() };
+        assert_eq!(analyze::<ChatStatistics>(&messages, &export).tokens.num_tokens, 0);
+        let east = Options { filter, zone: "+01:00".parse::<Zone>().unwrap(), ..Default::default() };
+        assert_eq!(analyze::<ChatStatistics>(&messages, &east).tokens.num_tokens, 1);
+    }
+
+    #[test]
+    fn filters_members_by_id_or_name() {
+        let message = message(1, 7, "hi");
+        let date = message.date;
+        for wanted in ["user7", "User 7"] {
+            assert!(MessageFilter { members: members(&[wanted]), ..Default::default() }.matches(&message, date), "{wanted}");
+        }
+        for unwanted in ["user8", "User 8", "user", "7", "chat7", "user 7"] {
+            assert!(!MessageFilter { members: members(&[unwanted]), ..Default::default() }.matches(&message, date), "{unwanted}");
+        }
+        assert!(MessageFilter { members: members(&["user8", "User 7"]), ..Default::default() }.matches(&message, date));
+
+        let anonymous = Message { from: Some(Person(Cow::Borrowed("Channel"))), ..Default::default() };
+        assert!(MessageFilter { members: members(&["Channel"]), ..Default::default() }.matches(&anonymous, date));
+    }
+
+    #[test]
+    fn exclusions_win() {
+        let message = message(1, 7, "hi");
+        let date = message.date;
+        assert!(!MessageFilter { exclude_members: members(&["user7"]), ..Default::default() }.matches(&message, date));
+        assert!(!MessageFilter { members: members(&["user7"]), exclude_members: members(&["User 7"]), ..Default::default() }.matches(&message, date));
+        assert!(MessageFilter { exclude_members: members(&["user8"]), ..Default::default() }.matches(&message, date));
+    }
+
+    #[test]
+    fn service_messages_belong_to_their_actor() {
+        let invite = service(1, Some(MemberId::User(7)), "User 7");
+        let date = invite.date;
+        assert!(MessageFilter { members: members(&["user7"]), ..Default::default() }.matches(&invite, date));
+        assert!(MessageFilter { members: members(&["User 7"]), ..Default::default() }.matches(&invite, date));
+        assert!(!MessageFilter { exclude_members: members(&["User 7"]), ..Default::default() }.matches(&invite, date));
+
+        let unnamed = service(2, None, "Old Name");
+        assert!(MessageFilter { members: members(&["Old Name"]), ..Default::default() }.matches(&unnamed, date));
+        assert!(!MessageFilter { members: members(&["user7"]), ..Default::default() }.matches(&unnamed, date));
+    }
+}
@@ -0,0 +1,103 @@
+use serde::Serialize;
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::model::{Message, MessageType};
+
+/// What an export can be converted to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Format {
+    /// One JSON message per line
+    #[default]
+    Jsonl,
+    /// `chat,id,date,from,from_id,type,text` rows under a header
+    Csv,
+    /// `[date] from: text` lines like a chat log, without service messages
+    Text,
+}
+
+/// Writes the messages of one or more chats out in a `Format`.
+pub struct Converter<W> {
+    writer: W,
+    format: Format,
+    started: bool,
+}
+
+#[derive(Serialize)]
+struct Line<'m> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chat: Option<&'m str>,
+    #[serde(flatten)]
+    message: &'m Message<'m>,
+}
+
+impl<W: Write> Converter<W> {
+    /// Writes to `writer`, which is best buffered.
+    pub fn new(writer: W, format: Format) -> Self {
+        Self { writer, format, started: false }
+    }
+
+    /// `chat` names the chat the messages come from in account exports.
+    pub fn write<'m, 'a: 'm>(&mut self, chat: Option<&str>, messages: impl IntoIterator<Item = &'m Message<'a>>) -> io::Result<()> {
+        match self.format {
+            Format::Jsonl => {
+                for message in messages {
+                    serde_json::to_writer(&mut self.writer, &Line { chat, message })?;
+                    self.writer.write_all(b"\n")?;
+                }
+            }
+            Format::Csv => {
+                if !self.started {
+                    writeln!(self.writer, "chat,id,date,from,from_id,type,text")?;
+                }
+                for message in messages {
+                    let from = message.from.as_ref().map(|from| from.0.as_ref()).unwrap_or_default();
+                    let from_id = message.from_id.map(|id| id.to_string()).unwrap_or_default();
+                    let message_type = match message.message_type {
+                        MessageType::Message => "message",
+                        MessageType::Service => "service",
+                    };
+                    writeln!(
+                        self.writer,
+                        "{},{},{},{},{from_id},{message_type},{}",
+                        csv_field(chat.unwrap_or_default()),
+                        message.id,
+                        message.date,
+                        csv_field(from),
+                        csv_field(&message.text())
+                    )?;
+                }
+            }
+            Format::Text => {
+                if let Some(chat) = chat {
+                    if self.started {
+                        writeln!(self.writer)?;
+                    }
+                    writeln!(self.writer, "== {chat} ==")?;
+                }
+                for message in messages.into_iter().filter(|message| message.message_type != MessageType::Service) {
+                    let from = message.from.as_ref().map(|from| from.0.as_ref()).unwrap_or("Deleted Account");
+                    writeln!(self.writer, "[{}] {from}: {}", message.date, message.text())?;
+                }
+            }
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    /// Hands `writer` back, to be flushed.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Quotes `field` if it has a comma, a quote or a line break.
+pub fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
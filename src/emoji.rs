@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::member::Member;
+use crate::merge_maps_with;
+use crate::model::{MessageType, TextEntity, TextEntityType};
+
+/// Emojis used overall and per member; skin tone variants count towards the emoji without one.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EmojiStatistics<'a> {
+    /// Occurrences of every emoji.
+    #[serde(borrow)]
+    pub emojis_map: HashMap<Cow<'a, str>, usize>,
+    /// `emojis_map` of every member.
+    #[serde(borrow)]
+    pub members_emojis_map: MembersMap<'a>,
+    /// Premium emojis, by the `document_id` of their sticker.
+    #[serde(borrow)]
+    pub custom_emojis_map: HashMap<Cow<'a, str>, usize>,
+    /// `custom_emojis_map` of every member.
+    #[serde(borrow)]
+    pub members_custom_emojis_map: MembersMap<'a>,
+}
+
+impl<'a> Analyzer<'a> for EmojiStatistics<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        if observation.message.message_type == MessageType::Service {
+            return;
+        }
+        for entity in &observation.message.text_entities {
+            self.observe_entity(observation.from.as_ref(), entity);
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_counts(&mut self.emojis_map, &mut self.members_emojis_map, other.emojis_map, other.members_emojis_map);
+        merge_counts(&mut self.custom_emojis_map, &mut self.members_custom_emojis_map, other.custom_emojis_map, other.members_custom_emojis_map);
+    }
+}
+
+impl<'a> EmojiStatistics<'a> {
+    fn observe_entity(&mut self, member: Option<&Member<'a>>, entity: &'a TextEntity<'a>) {
+        if entity.text_type == TextEntityType::CustomEmoji {
+            // Its text is just the fallback for clients that can't show it.
+            if let Some(document_id) = &entity.document_id {
+                count(&mut self.custom_emojis_map, &mut self.members_custom_emojis_map, member, Cow::Borrowed(document_id));
+            }
+            return;
+        }
+        if entity.text_type.is_meta() || is_emoji_free(&entity.text) {
+            return;
+        }
+        for grapheme in entity.text.graphemes(true) {
+            let Some(emoji) = emojis::get(grapheme) else {
+                continue;
+            };
+            let base = emoji.skin_tones().and_then(|mut variants| variants.next()).unwrap_or(emoji);
+            count(&mut self.emojis_map, &mut self.members_emojis_map, member, Cow::Borrowed(base.as_str()));
+        }
+    }
+
+    /// Copies the emojis borrowed from the messages.
+    pub fn into_owned(self) -> EmojiStatistics<'static> {
+        let owned = |map: HashMap<Cow<'a, str>, usize>| map.into_iter().map(|(emoji, occurences)| (Cow::Owned(emoji.into_owned()), occurences)).collect();
+        let owned_members = |map: MembersMap<'a>| map.into_iter().map(|(member, map)| (member.into_owned(), owned(map))).collect();
+        EmojiStatistics {
+            emojis_map: owned(self.emojis_map),
+            members_emojis_map: owned_members(self.members_emojis_map),
+            custom_emojis_map: owned(self.custom_emojis_map),
+            members_custom_emojis_map: owned_members(self.members_custom_emojis_map),
+        }
+    }
+}
+
+/// Whether `text` certainly has no emoji, without segmenting it into graphemes.
+///
+/// No ASCII character is an emoji on its own (keycaps need U+FE0F and U+20E3) and neither are letters, digits or spaces,
+/// except for the few letterlike ones below.
+pub fn is_emoji_free(text: &str) -> bool {
+    text.chars().all(|c| c.is_ascii() || ((c.is_alphanumeric() || c.is_whitespace()) && !matches!(c, 'ℹ' | 'Ⓜ' | '🅰' | '🅱' | '🅾' | '🅿')))
+}
+
+type MembersMap<'a> = HashMap<Member<'a>, HashMap<Cow<'a, str>, usize>>;
+
+fn count<'a>(map: &mut HashMap<Cow<'a, str>, usize>, members_map: &mut MembersMap<'a>, member: Option<&Member<'a>>, emoji: Cow<'a, str>) {
+    if let Some(member) = member {
+        *members_map.entry(member.clone()).or_default().entry(emoji.clone()).or_insert(0) += 1;
+    }
+    *map.entry(emoji).or_insert(0) += 1;
+}
+
+fn merge_counts<'a>(map: &mut HashMap<Cow<'a, str>, usize>, members_map: &mut MembersMap<'a>, other: HashMap<Cow<'a, str>, usize>, other_members: MembersMap<'a>) {
+    merge_maps_with(map, other, |map, emoji, occurences| *map.entry(emoji).or_insert(0) += occurences);
+    for (member, other) in other_members {
+        let mergee = members_map.entry(member).or_default();
+        merge_maps_with(mergee, other, |mergee, emoji, occurences| *mergee.entry(emoji).or_insert(0) += occurences);
+    }
+}
@@ -0,0 +1,81 @@
+use std::io;
+use std::path::PathBuf;
+
+use crate::schema::{without_position, SchemaError};
+
+/// `Result` with the library's `Error`.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong between reading an export and writing its statistics.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reading an export or a word list, or writing the report.
+    #[error("{}: {source}", path.display())]
+    Io {
+        /// The file or directory.
+        path: PathBuf,
+        /// What the OS reported.
+        source: io::Error,
+    },
+    /// The export isn't valid JSON, or isn't shaped like an export.
+    #[error("invalid export at line {line} column {column}{}: {}", json_context(.location, .message_id), without_position(.source))]
+    Json {
+        /// Where in the export, counting from 1.
+        line: usize,
+        /// Where on the line, counting from 1.
+        column: usize,
+        /// JSON path of the message being read, e.g. `chats.list[3].messages[12]`.
+        location: Option<String>,
+        /// The id of the last message read before it.
+        message_id: Option<u64>,
+        /// What serde_json reported.
+        source: serde_json::Error,
+    },
+    /// Messages that are valid JSON but don't match the `Message` model.
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+    /// A line of a lemma dictionary that isn't `lemma form`.
+    #[error("{}:{line}: expected `lemma form`, got `{text}`", path.display())]
+    Lemmas {
+        /// The dictionary.
+        path: PathBuf,
+        /// Counting from 1.
+        line: usize,
+        /// The line as it was read.
+        text: String,
+    },
+    /// The analysis can't run with the given options.
+    #[error("{0}")]
+    Analysis(String),
+}
+
+impl Error {
+    /// Attaches `path` to an `io::Error`, for `map_err`.
+    pub fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+
+    pub(crate) fn json(source: serde_json::Error, location: Option<String>, message_id: Option<u64>) -> Self {
+        Error::Json { line: source.line(), column: source.column(), location, message_id, source }
+    }
+
+    /// What the CLI exits with; 2 is left to argument errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Analysis(_) => 1,
+            Error::Io { .. } => 3,
+            Error::Json { .. } => 4,
+            Error::Schema(_) => 5,
+            Error::Lemmas { .. } => 6,
+        }
+    }
+}
+
+fn json_context(location: &Option<String>, message_id: &Option<u64>) -> String {
+    match (location, message_id) {
+        (Some(location), Some(id)) => format!(" (in {location}, after message {id})"),
+        (Some(location), None) => format!(" (in {location})"),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,316 @@
+use chrono::{DateTime, NaiveDateTime};
+use scraper::{ElementRef, Html, Node, Selector};
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::analyzer::{self, Detached};
+use crate::error::{Error, Result};
+use crate::model::{Message, MessageType, Person, TextEntity, TextEntityType};
+use crate::Options;
+
+/// Gathers statistics from an HTML export one `messagesN.html` page at a time.
+///
+/// `path` is either the export directory or any of its pages.
+pub fn gather<A: Detached>(path: &Path, options: &Options) -> Result<A> {
+    let mut stat = A::default();
+    let mut reader = PageReader::default();
+    for page in pages(path)? {
+        let messages = reader.read(&fs::read_to_string(&page).map_err(Error::io(page))?);
+        stat.merge(A::detach(analyzer::fold(&messages, options)));
+    }
+    stat.finish();
+    Ok(stat)
+}
+
+/// Reads every message of an HTML export at once.
+pub fn read(path: &Path) -> Result<Vec<Message<'static>>> {
+    let mut messages = Vec::new();
+    let mut reader = PageReader::default();
+    for page in pages(path)? {
+        messages.extend(reader.read(&fs::read_to_string(&page).map_err(Error::io(page))?));
+    }
+    Ok(messages)
+}
+
+/// Lists `messages.html`, `messages2.html`, ... in page order.
+fn pages(path: &Path) -> Result<Vec<PathBuf>> {
+    // A bare `messages.html` has an empty parent rather than none.
+    let dir = if path.is_dir() { path } else { path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")) };
+
+    let mut pages = Vec::new();
+    for entry in fs::read_dir(dir).map_err(Error::io(dir))? {
+        let path = entry.map_err(Error::io(dir))?.path();
+        let Some(page) = path.file_name().and_then(|name| name.to_str()).and_then(page_number) else {
+            continue;
+        };
+        pages.push((page, path));
+    }
+    if pages.is_empty() {
+        return Err(Error::Io { path: dir.to_path_buf(), source: io::Error::new(io::ErrorKind::NotFound, "no messages.html") });
+    }
+    pages.sort_unstable();
+
+    Ok(pages.into_iter().map(|(_, path)| path).collect())
+}
+
+fn page_number(file_name: &str) -> Option<usize> {
+    let number = file_name.strip_prefix("messages")?.strip_suffix(".html")?;
+    if number.is_empty() {
+        Some(1)
+    } else {
+        number.parse().ok()
+    }
+}
+
+/// Carries the state that "joined" messages inherit across pages.
+#[derive(Default)]
+struct PageReader {
+    from: Option<String>,
+    date: NaiveDateTime,
+    date_unixtime: Option<i64>,
+}
+
+impl PageReader {
+    fn read(&mut self, content: &str) -> Vec<Message<'static>> {
+        let document = Html::parse_document(content);
+        let selector = Selector::parse("div.history > div.message").unwrap();
+
+        document.select(&selector).filter_map(|element| self.read_message(element)).collect()
+    }
+
+    fn read_message(&mut self, element: ElementRef) -> Option<Message<'static>> {
+        // Date separators are service messages with negative ids.
+        let id = element.attr("id")?.strip_prefix("message")?.parse().ok()?;
+        let body = children(element).find(|child| has_class(child, "body"))?;
+
+        if has_class(&element, "service") {
+            return Some(Message {
+                id,
+                message_type: MessageType::Service,
+                date: self.date,
+                date_unixtime: self.date_unixtime,
+                ..Default::default()
+            });
+        }
+
+        for child in children(body) {
+            if has_class(&child, "date") {
+                if let Some((date, date_unixtime)) = child.attr("title").and_then(parse_date) {
+                    (self.date, self.date_unixtime) = (date, date_unixtime);
+                }
+            } else if has_class(&child, "from_name") {
+                self.from = Some(own_text(child));
+            }
+        }
+        // Forwarded messages keep their text inside a nested `forwarded body`.
+        let text_entities = children(body)
+            .chain(children(body).filter(|child| has_class(child, "forwarded")).flat_map(children))
+            .find(|child| has_class(child, "text"))
+            .map(text_entities)
+            .unwrap_or_default();
+
+        Some(Message {
+            id,
+            message_type: MessageType::Message,
+            date: self.date,
+            date_unixtime: self.date_unixtime,
+            from: self.from.clone().map(|from| Person(Cow::Owned(from))),
+            text_entities,
+            ..Default::default()
+        })
+    }
+}
+
+/// Parses `12.01.2023 15:04:05 UTC+02:00` into the local time and the Unix time, or only the local time when older exports leave the offset out.
+fn parse_date(title: &str) -> Option<(NaiveDateTime, Option<i64>)> {
+    if let Ok(date) = DateTime::parse_from_str(title, "%d.%m.%Y %H:%M:%S UTC%:z") {
+        return Some((date.naive_local(), Some(date.timestamp())));
+    }
+    let date = title.get(..19)?;
+    NaiveDateTime::parse_from_str(date, "%d.%m.%Y %H:%M:%S").ok().map(|date| (date, None))
+}
+
+fn text_entities(text: ElementRef) -> Vec<TextEntity<'static>> {
+    let mut entities: Vec<TextEntity> = Vec::new();
+    for node in text.children() {
+        let (text_type, text) = match node.value() {
+            Node::Text(text) => (TextEntityType::Plain, text.to_string()),
+            Node::Element(element) if element.name() == "br" => (TextEntityType::Plain, "\n".to_string()),
+            Node::Element(_) => {
+                let Some(element) = ElementRef::wrap(node) else {
+                    continue;
+                };
+                (entity_type(element), element.text().collect())
+            }
+            _ => continue,
+        };
+        // Consecutive plain runs are split around <br>, Telegram keeps them as a single entity.
+        match entities.last_mut() {
+            Some(last) if last.text_type == TextEntityType::Plain && text_type == TextEntityType::Plain => last.text.to_mut().push_str(&text),
+            _ => entities.push(TextEntity { text_type, text: Cow::Owned(text), ..Default::default() }),
+        }
+    }
+    entities
+}
+
+fn entity_type(element: ElementRef) -> TextEntityType {
+    let value = element.value();
+    match value.name() {
+        "strong" | "b" => TextEntityType::Bold,
+        "em" | "i" => TextEntityType::Italic,
+        "u" => TextEntityType::Underline,
+        "s" | "del" => TextEntityType::Strikethrough,
+        "code" => TextEntityType::Code,
+        "pre" => TextEntityType::Pre,
+        "span" if value.classes().any(|class| class.contains("spoiler")) => TextEntityType::Spoiler,
+        "a" => link_type(element),
+        _ => TextEntityType::Plain,
+    }
+}
+
+fn link_type(element: ElementRef) -> TextEntityType {
+    let href = element.attr("href").unwrap_or_default();
+    let onclick = element.attr("onclick").unwrap_or_default();
+    let text: String = element.text().collect();
+
+    if onclick.contains("ShowHashtag") {
+        TextEntityType::Hashtag
+    } else if onclick.contains("ShowCashtag") {
+        TextEntityType::Cashtag
+    } else if onclick.contains("ShowBotCommand") {
+        TextEntityType::BotCommand
+    } else if onclick.contains("ShowMentionName") {
+        TextEntityType::MentionName
+    } else if href.starts_with("mailto:") {
+        TextEntityType::Email
+    } else if href.starts_with("tel:") {
+        TextEntityType::Phone
+    } else if text.starts_with('@') {
+        TextEntityType::Mention
+    } else if href == text {
+        TextEntityType::Link
+    } else {
+        TextEntityType::TextLink
+    }
+}
+
+fn children<'a>(element: ElementRef<'a>) -> impl Iterator<Item = ElementRef<'a>> {
+    element.children().filter_map(ElementRef::wrap)
+}
+
+fn has_class(element: &ElementRef, class: &str) -> bool {
+    element.value().classes().any(|c| c == class)
+}
+
+/// The element's own text, without nested "via @bot" spans and the like.
+fn own_text(element: ElementRef) -> String {
+    let text: String = element.children().filter_map(|node| node.value().as_text()).map(|text| &**text).collect();
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn page(messages: &str) -> String {
+        format!(r#"<html><body><div class="page_body chat_page"><div class="history">{messages}</div></div></body></html>"#)
+    }
+
+    fn entities(html: &str) -> Vec<(TextEntityType, String)> {
+        let document = Html::parse_fragment(html);
+        let text = document.select(&Selector::parse("div.text").unwrap()).next().unwrap();
+        text_entities(text).into_iter().map(|entity| (entity.text_type, entity.text.into_owned())).collect()
+    }
+
+    #[test]
+    fn numbers_pages() {
+        assert_eq!(page_number("messages.html"), Some(1));
+        assert_eq!(page_number("messages2.html"), Some(2));
+        assert_eq!(page_number("messages10.html"), Some(10));
+        for name in ["messages.json", "message.html", "messages_2.html", "messagesx.html", "photos.html", "result.json"] {
+            assert_eq!(page_number(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn lists_pages_in_order() {
+        let dir = std::env::temp_dir().join(format!("teleparser-pages-{}", std::process::id()));
+        fs::create_dir_all(dir.join("photos")).unwrap();
+        for name in ["messages10.html", "messages2.html", "messages.html", "style.css", "result.json"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let names = |pages: Vec<PathBuf>| -> Vec<String> { pages.iter().map(|page| page.file_name().unwrap().to_string_lossy().into_owned()).collect() };
+
+        assert_eq!(names(pages(&dir).unwrap()), ["messages.html", "messages2.html", "messages10.html"]);
+        assert_eq!(names(pages(&dir.join("messages2.html")).unwrap()), ["messages.html", "messages2.html", "messages10.html"]);
+        assert!(pages(&dir.join("photos")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_bare_page_names_from_the_current_directory() {
+        // Tests run in the crate root, which has no pages: the error names the directory searched.
+        match pages(Path::new("messages.html")) {
+            Err(Error::Io { path, .. }) => assert_eq!(path, Path::new(".")),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_dates_with_offsets() {
+        let local = NaiveDate::from_ymd_opt(2023, 1, 12).unwrap().and_hms_opt(15, 4, 5).unwrap();
+        assert_eq!(parse_date("12.01.2023 15:04:05 UTC+02:00"), Some((local, Some(1673528645))));
+        assert_eq!(parse_date("12.01.2023 15:04:05 UTC-05:30"), Some((local, Some(1673555645))));
+    }
+
+    #[test]
+    fn parses_dates_without_offsets() {
+        let local = NaiveDate::from_ymd_opt(2023, 1, 12).unwrap().and_hms_opt(15, 4, 5).unwrap();
+        assert_eq!(parse_date("12.01.2023 15:04:05"), Some((local, None)));
+        assert_eq!(parse_date("12.01.2023"), None);
+        assert_eq!(parse_date("2023-01-12 15:04:05"), None);
+    }
+
+    #[test]
+    fn merges_plain_runs_around_line_breaks() {
+        let html = r#"<div class="text">first line<br>second <strong>bold</strong> third<br><br>last <a href="https://example.com">https://example.com</a></div>"#;
+        assert_eq!(entities(html), [
+            (TextEntityType::Plain, "first line\nsecond ".to_string()),
+            (TextEntityType::Bold, "bold".to_string()),
+            (TextEntityType::Plain, " third\n\nlast ".to_string()),
+            (TextEntityType::Link, "https://example.com".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn joined_messages_inherit_across_pages() {
+        let first = page(concat!(
+            r#"<div class="message service" id="message-1"><div class="body details">12 January 2023</div></div>"#,
+            r#"<div class="message default clearfix" id="message1"><div class="body">"#,
+            r#"<div class="pull_right date details" title="12.01.2023 15:04:05 UTC+02:00">15:04</div>"#,
+            r#"<div class="from_name">Alice</div><div class="text">hello</div></div></div>"#,
+        ));
+        let second = page(concat!(
+            r#"<div class="message default clearfix joined" id="message2"><div class="body">"#,
+            r#"<div class="pull_right date details" title="12.01.2023 15:05:00 UTC+02:00">15:05</div>"#,
+            r#"<div class="text">again</div></div></div>"#,
+            r#"<div class="message default clearfix" id="message3"><div class="body">"#,
+            r#"<div class="pull_right date details" title="12.01.2023 15:06:00">15:06</div>"#,
+            r#"<div class="from_name">Bob <span class="details">via @bot</span></div>"#,
+            r#"<div class="forwarded body"><div class="from_name">Carol</div><div class="text">forwarded</div></div></div></div>"#,
+        ));
+
+        let mut reader = PageReader::default();
+        let mut messages = reader.read(&first);
+        messages.extend(reader.read(&second));
+
+        let summary: Vec<_> = messages.iter().map(|message| (message.id, message.from.as_ref().map(|from| from.0.as_ref()), message.text())).collect();
+        assert_eq!(summary, [(1, Some("Alice"), "hello".into()), (2, Some("Alice"), "again".into()), (3, Some("Bob"), "forwarded".into())]);
+        assert_eq!(messages[1].date_unixtime, Some(1673528700));
+        assert_eq!(messages[1].date, NaiveDate::from_ymd_opt(2023, 1, 12).unwrap().and_hms_opt(15, 5, 0).unwrap());
+        assert_eq!(messages[2].date_unixtime, None);
+    }
+}
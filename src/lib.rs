@@ -0,0 +1,298 @@
+//! Statistics about Telegram Desktop exports.
+//!
+//! Exports are read by [`stream::collect`], which borrows every string it can from a JSON export held in memory,
+//! [`stream::gather`], which never holds more than a batch of messages of a JSON export, or [`html::gather`].
+//! Statistics are [`Analyzer`]s gathered in parallel over the messages of a chat, [`ChatStatistics`] runs every bundled one.
+//! Custom analyzers run through [`analyze`], or alongside the bundled ones as a tuple, see [`Detached`].
+//!
+//! ```no_run
+//! use teleparser::schema::Schema;
+//! use teleparser::{ChatFilter, ChatStatistics, Export, Options};
+//!
+//! let content = std::fs::read_to_string("result.json")?;
+//! let schema = Schema::default();
+//! let export = teleparser::stream::collect(&content, &ChatFilter::default(), &schema)?;
+//! for skipped in schema.check()? {
+//!     eprintln!("skipping {skipped}");
+//! }
+//! if let Export::Chat(chat) = &export {
+//!     let stat = ChatStatistics::gather(chat, &Options::default());
+//!     println!("{} distinct tokens", stat.tokens.num_tokens);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+#![warn(missing_docs)]
+
+use serde::{Deserialize, Serialize};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::Path;
+
+pub use account::{AccountStatistics, ChatFilter, Export, ExportStatistics};
+pub use analyzer::{analyze, Analyzer, Detached, MessageFilter, Observation};
+pub use error::{Error, Result};
+pub use model::{Chat, ChatType, Message};
+
+use activity::{Activity, MemberSummary};
+use emoji::EmojiStatistics;
+use member::Members;
+use morphology::Morphology;
+use ngram::Ngrams;
+use normalize::Normalization;
+use service::ServiceStatistics;
+use stopwords::Stopwords;
+use timeline::Timeline;
+use tokenizer::{Tokenizer, Words};
+use tokens::TokenStatistics;
+use top::TopStatistics;
+use zone::Zone;
+
+/// Single-chat and account exports, and the statistics of either.
+pub mod account;
+/// What every member did besides the words they used, and a summary of each member.
+pub mod activity;
+/// The `Analyzer` trait every statistic implements and the parallel pass that drives them.
+pub mod analyzer;
+/// Writing messages out as JSON lines, CSV or a plain text log.
+pub mod convert;
+/// Emoji and custom emoji counts.
+pub mod emoji;
+/// The errors of the library and the exit codes the CLI turns them into.
+pub mod error;
+/// Reading HTML exports.
+pub mod html;
+/// Who wrote a message, and the names every member went by.
+pub mod member;
+/// The messages and chats of an export, as Telegram Desktop writes them.
+pub mod model;
+/// Counting words by their lemma or stem.
+pub mod morphology;
+/// Counts of adjacent words.
+pub mod ngram;
+/// Unicode normalization of tokens.
+pub mod normalize;
+/// Checking messages against the `Message` model one at a time.
+pub mod schema;
+/// Finding messages by their text.
+pub mod search;
+/// Statistics of service messages: membership, titles, photos, pins and calls.
+pub mod service;
+/// Words left out of the counts.
+pub mod stopwords;
+/// Reading JSON exports, whole or a batch of messages at a time.
+pub mod stream;
+/// Messages and tokens over time.
+pub mod timeline;
+/// Splitting text into tokens.
+pub mod tokenizer;
+/// Token counts, overall and by member.
+pub mod tokens;
+/// The most used tokens.
+pub mod top;
+/// The time zone dates are counted in.
+pub mod zone;
+
+/// How an export was saved by Telegram Desktop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum InputFormat {
+    /// `result.json`
+    Json,
+    /// `messages.html`, `messages2.html`, ... (pass either the directory or a page)
+    Html,
+}
+
+impl InputFormat {
+    /// Guesses the format of the export at `path`.
+    pub fn detect(path: &Path) -> Self {
+        if path.is_dir() || path.extension().is_some_and(|extension| extension == "html") {
+            InputFormat::Html
+        } else {
+            InputFormat::Json
+        }
+    }
+}
+
+/// What `ChatStatistics::gather` counts besides single tokens.
+#[derive(Debug)]
+pub struct Options {
+    /// Splits the text of every entity into tokens.
+    pub tokenizer: Box<dyn Tokenizer>,
+    /// Applied to every token before it is counted.
+    pub normalization: Normalization,
+    /// Tokens counted apart rather than with the others.
+    pub stopwords: Stopwords,
+    /// Counts tokens by their lemma or stem, if enabled.
+    pub morphology: Morphology,
+    /// Every `n` to count n-grams for; below 2 there is nothing to pair, so those are skipped.
+    pub ngrams: Vec<usize>,
+    /// The zone dates and hours are counted in.
+    pub zone: Zone,
+    /// Which messages are analyzed at all.
+    pub filter: MessageFilter,
+}
+
+/// Unicode words, normalized like the CLI does by default, without stopwords, morphology or n-grams, in the export's time, over every message.
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tokenizer: Box::new(Words),
+            normalization: Normalization::default(),
+            stopwords: Stopwords::default(),
+            morphology: Morphology::default(),
+            ngrams: Vec::new(),
+            zone: Zone::default(),
+            filter: MessageFilter::default(),
+        }
+    }
+}
+
+/// A word as it is counted, after normalization and morphology.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+pub struct Token<'a>(pub Cow<'a, str>);
+
+impl Token<'_> {
+    /// Detaches the token from the text it was read from.
+    pub fn into_owned(self) -> Token<'static> {
+        Token(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl<'a> From<&'a str> for Token<'a> {
+    fn from(value: &'a str) -> Self {
+        Token(value.into())
+    }
+}
+impl<'a> From<String> for Token<'a> {
+    fn from(value: String) -> Self {
+        Token(value.into())
+    }
+}
+
+/// Every bundled statistic of a chat, gathered in a single pass.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChatStatistics<'a> {
+    /// Reported at the top level of the JSON report.
+    #[serde(borrow, flatten)]
+    pub tokens: TokenStatistics<'a>,
+    /// The names every member went by.
+    #[serde(borrow)]
+    pub members: Members<'a>,
+    /// Emojis and the emoji variants of tokens.
+    #[serde(borrow, default)]
+    pub emojis: EmojiStatistics<'a>,
+    /// Empty unless `Options::ngrams` asks for n-grams.
+    #[serde(borrow, default, skip_serializing_if = "Ngrams::is_empty")]
+    pub ngrams: Ngrams<'a>,
+    /// Joins, leaves, renames and pins.
+    #[serde(borrow)]
+    pub service: ServiceStatistics<'a>,
+    /// Messages and tokens per day.
+    #[serde(borrow, default)]
+    pub timeline: Timeline<'a>,
+    /// Only filled in by `rank`.
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    pub top: Option<TopStatistics<'a>>,
+    /// Left out of the report, `summarize` sums it up.
+    #[serde(skip)]
+    pub activity: Activity<'a>,
+    /// Only filled in by `summarize`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Vec<MemberSummary>>,
+}
+impl<'a> ChatStatistics<'a> {
+    /// Gathers every statistic of `chat`, borrowing what it can from its messages.
+    pub fn gather(chat: &'a Chat, options: &Options) -> Self {
+        Self::gather_messages(&chat.messages, options)
+    }
+    /// Gathers every statistic of `messages`, the same as `analyze`.
+    pub fn gather_messages(messages: &'a [Message], options: &Options) -> Self {
+        analyzer::analyze(messages, options)
+    }
+    /// Ranks the `n` most used tokens overall and of every member.
+    pub fn rank(&mut self, n: usize) {
+        self.top = Some(TopStatistics::new(n, &self.tokens.tokens_map, &self.tokens.members_tokens_map));
+    }
+    /// Summarizes the activity of every member.
+    pub fn summarize(&mut self) {
+        self.summary = Some(MemberSummary::all(&self.activity, &self.tokens, &self.members));
+    }
+    /// Detaches the statistics from the messages they were gathered from.
+    pub fn into_owned(self) -> ChatStatistics<'static> {
+        ChatStatistics {
+            tokens: self.tokens.into_owned(),
+            members: self.members.into_owned(),
+            emojis: self.emojis.into_owned(),
+            ngrams: self.ngrams.into_owned(),
+            service: self.service.into_owned(),
+            timeline: self.timeline.into_owned(),
+            top: self.top.map(TopStatistics::into_owned),
+            activity: self.activity.into_owned(),
+            summary: self.summary,
+        }
+    }
+}
+
+impl Detached for ChatStatistics<'static> {
+    type Borrowed<'a> = ChatStatistics<'a>;
+    fn detach(analyzer: ChatStatistics<'_>) -> Self {
+        analyzer.into_owned()
+    }
+}
+
+impl<'a> Analyzer<'a> for ChatStatistics<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        self.tokens.observe(observation);
+        self.members.observe(observation);
+        self.emojis.observe(observation);
+        self.ngrams.observe(observation);
+        self.service.observe(observation);
+        self.timeline.observe(observation);
+        self.activity.observe(observation);
+    }
+    fn merge(&mut self, other: Self) {
+        self.tokens.merge(other.tokens);
+        self.members.merge(other.members);
+        self.emojis.merge(other.emojis);
+        self.ngrams.merge(other.ngrams);
+        self.service.merge(other.service);
+        self.timeline.merge(other.timeline);
+        self.activity.merge(other.activity);
+        // Stale now, `rank` and `summarize` again once everything is merged.
+        self.top = None;
+        self.summary = None;
+    }
+    fn finish(&mut self) {
+        self.tokens.finish();
+        self.members.finish();
+        self.emojis.finish();
+        self.ngrams.finish();
+        self.service.finish();
+        self.timeline.finish();
+        self.activity.finish();
+    }
+}
+
+/// Strips emojis out of `token`, which stays borrowed unless it had any.
+pub(crate) fn remove_emojis(token: &str) -> Cow<'_, str> {
+    use unicode_segmentation::UnicodeSegmentation;
+    if emoji::is_emoji_free(token) {
+        return Cow::Borrowed(token);
+    }
+    let is_not_emoji = |x: &&str| emojis::get(x).is_none();
+    if token.graphemes(true).all(|grapheme| is_not_emoji(&grapheme)) {
+        return Cow::Borrowed(token);
+    }
+    Cow::Owned(token.graphemes(true).filter(is_not_emoji).collect())
+}
+
+pub(crate) fn merge_maps_with<K, F>(dst: &mut HashMap<K, usize>, src: HashMap<K, usize>, f: F) 
+where K: Eq + PartialEq + Hash,
+F: Fn(&mut HashMap<K, usize>, K, usize) {
+    for (key, occurences) in src {
+        f(dst, key, occurences)
+    }
+}
\ No newline at end of file
@@ -1,210 +1,633 @@
-use chrono::NaiveDateTime;
-use clap::Parser;
-use serde::{Deserialize, Serialize};
-
-use rayon::prelude::*;
+use chrono::{NaiveDate, Weekday};
+use clap::{Args, Parser, Subcommand};
+use regex::RegexBuilder;
+use serde::Serialize;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
-use std::{fs, io};
+use std::process::ExitCode;
+use std::fs;
+
+use teleparser::account::{AccountStatistics, ChatFilter, Export, ExportStatistics};
+use teleparser::convert::{csv_field, Converter, Format};
+use teleparser::member::{Member, Members};
+use teleparser::model::{ChatType, Message};
+use teleparser::morphology::{self, Language, Morphology};
+use teleparser::normalize::Normalization;
+use teleparser::schema::{Schema, SchemaMode};
+use teleparser::stopwords::Stopwords;
+use teleparser::timeline::{Bucket, Period, Series};
+use teleparser::tokenizer::TokenizerKind;
+use teleparser::top::Ranked;
+use teleparser::zone::Zone;
+use teleparser::{html, search, stream, ChatStatistics, Error, InputFormat, MessageFilter, Options, Result};
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Output piped into `head` and the like is closed early on purpose.
+        Err(Error::Io { source, .. }) if source.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let input = match &cli.command {
+        Command::Stats(args) => &args.input,
+        Command::Top(args) => &args.input,
+        Command::Members(args) => &args.input,
+        Command::Search(args) => &args.input,
+        Command::Timeline(args) => &args.input,
+        Command::Export(args) => &args.input,
+    };
+    if let Some(jobs) = input.jobs {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global();
+        pool.map_err(|error| Error::Analysis(format!("can't start {jobs} threads: {error}")))?;
+    }
+
+    match cli.command {
+        Command::Stats(args) => stats(args),
+        Command::Top(args) => top(args),
+        Command::Members(args) => members(args),
+        Command::Search(args) => search(args),
+        Command::Timeline(args) => timeline(args),
+        Command::Export(args) => export(args),
+    }
+}
+
+fn stats(args: StatsArgs) -> Result<()> {
+    let (metadata, options) = args.analysis.options()?;
+
+    gather(&args.input, &args.streaming, &options, |mut stat| {
+        if let Some(n) = args.top {
+            stat.rank(n);
+        }
+        stat.summarize();
+        let report = Report { metadata, statistics: stat };
+        // Only now, so that a failed run leaves the previous report alone.
+        let file = fs::File::create(&args.output).map_err(Error::io(&args.output))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &report).map_err(|error| Error::Io { path: args.output, source: error.into() })
+    })
+}
+
+fn top(args: TopArgs) -> Result<()> {
+    let (_, options) = args.analysis.options()?;
+
+    gather(&args.input, &args.streaming, &options, |stat| {
+        let mut stat = match stat {
+            ExportStatistics::Chat(stat) => stat,
+            ExportStatistics::Account(account) => account.total,
+        };
+        stat.rank(args.n);
+        let top = stat.top.unwrap_or_default();
+        print(|out| {
+            if args.json {
+                return serde_json::to_writer_pretty(&mut *out, &top).map_err(io::Error::from);
+            }
+            write_ranked(out, &top.overall)?;
+            if args.members {
+                let mut members: Vec<_> = top.members.iter().map(|(member, ranked)| (label(member, &stat.members), ranked)).collect();
+                members.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+                for (label, ranked) in members {
+                    writeln!(out, "\n{label}")?;
+                    write_ranked(out, ranked)?;
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+fn members(args: MembersArgs) -> Result<()> {
+    let (_, options) = args.analysis.options()?;
+
+    gather(&args.input, &args.streaming, &options, |stat| {
+        let mut stat = match stat {
+            ExportStatistics::Chat(stat) => stat,
+            ExportStatistics::Account(account) => account.total,
+        };
+        stat.summarize();
+        let summary = stat.summary.unwrap_or_default();
+        print(|out| {
+            if args.json {
+                return serde_json::to_writer_pretty(&mut *out, &summary).map_err(io::Error::from);
+            }
+            writeln!(
+                out,
+                "member\tname\tmessages\tshare\ttokens\tvocabulary\taverage length\tmedian length\tfirst message\tlast message\t\
+                 hour\tweekday\treplies sent\treplies received\tedits\tforwards\tmedia"
+            )?;
+            for member in summary {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{:.2}%\t{}\t{}\t{:.1}\t{:.1}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    member.member,
+                    member.name.unwrap_or_default(),
+                    member.messages,
+                    member.share * 100.0,
+                    member.tokens,
+                    member.vocabulary,
+                    member.average_length,
+                    member.median_length,
+                    member.first_message,
+                    member.last_message,
+                    member.most_active_hour,
+                    member.most_active_weekday,
+                    member.replies_sent,
+                    member.replies_received,
+                    member.edits,
+                    member.forwards,
+                    member.media
+                )?;
+            }
+            Ok(())
+        })
+    })
+}
 
-const SEPARATORS: [char; 12] = [' ', ',', '.','(', ')', '-', '!', '?', '\'', '\"', '\n', '\t'];
+fn search(args: SearchArgs) -> Result<()> {
+    let pattern = RegexBuilder::new(&args.pattern)
+        .case_insensitive(args.ignore_case)
+        .build()
+        .map_err(|error| Error::Analysis(error.to_string()))?;
 
-fn main() -> io::Result<()> {
-    let cli = Cli::parse();
+    load(&args.input, |chats| {
+        print(|out| {
+            let mut converter = Converter::new(out, args.to);
+            for chat in chats {
+                let found = search::search(&chat.messages, &pattern);
+                if !found.is_empty() {
+                    converter.write(chat.name.as_deref(), found)?;
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+fn timeline(args: TimelineArgs) -> Result<()> {
+    let (_, options) = args.analysis.options()?;
+
+    gather(&args.input, &args.streaming, &options, |stat| {
+        let stat = match stat {
+            ExportStatistics::Chat(stat) => stat,
+            ExportStatistics::Account(account) => account.total,
+        };
+        let mut series = vec![(None, &stat.timeline.overall)];
+        if args.members {
+            let mut members: Vec<_> = stat.timeline.members.iter().map(|(member, series)| (Some(label(member, &stat.members)), series)).collect();
+            members.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            series.extend(members);
+        }
+        print(|out| {
+            if args.json {
+                let series: Vec<_> = series.iter().map(|(member, series)| Plot::new(member.as_deref(), series, args.by)).collect();
+                return serde_json::to_writer_pretty(&mut *out, &series).map_err(io::Error::from);
+            }
+            // Long format, one row per member and period or hour, which plotting tools take as is.
+            if args.heatmap {
+                writeln!(out, "member,weekday,hour,messages")?;
+            } else {
+                writeln!(out, "member,start,messages,tokens")?;
+            }
+            for (member, series) in series {
+                let member = csv_field(member.as_deref().unwrap_or_default());
+                if args.heatmap {
+                    for (weekday, hours) in WEEKDAYS.iter().zip(&series.heatmap) {
+                        for (hour, messages) in hours.iter().enumerate() {
+                            writeln!(out, "{member},{weekday},{hour},{messages}")?;
+                        }
+                    }
+                } else {
+                    for bucket in series.by(args.by) {
+                        writeln!(out, "{member},{},{},{}", bucket.start, bucket.counts.messages, bucket.counts.tokens)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+/// A `Series` bucketed for plotting, `member` is `None` for the whole chat.
+#[derive(Serialize)]
+struct Plot<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member: Option<&'a str>,
+    period: Period,
+    buckets: Vec<Bucket>,
+    heatmap: &'a [[usize; 24]; 7],
+}
+
+impl<'a> Plot<'a> {
+    fn new(member: Option<&'a str>, series: &'a Series, period: Period) -> Self {
+        Plot { member, period, buckets: series.by(period), heatmap: &series.heatmap }
+    }
+}
+
+fn export(args: ExportArgs) -> Result<()> {
+    let (writer, path): (Box<dyn Write>, _) = match &args.output {
+        Some(path) => (Box::new(fs::File::create(path).map_err(Error::io(path))?), path.clone()),
+        None => (Box::new(io::stdout().lock()), PathBuf::from("<stdout>")),
+    };
+
+    load(&args.input, |chats| {
+        let mut converter = Converter::new(BufWriter::new(writer), args.to);
+        for chat in chats {
+            converter.write(chat.name.as_deref(), &chat.messages).map_err(Error::io(&path))?;
+        }
+        converter.into_inner().flush().map_err(Error::io(&path))
+    })
+}
+
+/// Gathers every statistic of the chats selected by `input` and hands them to `f`.
+fn gather<T>(input: &Input, streaming: &Streaming, options: &Options, f: impl FnOnce(ExportStatistics<ChatStatistics>) -> Result<T>) -> Result<T> {
+    let schema = Schema::new(input.schema);
+    if input.format() == InputFormat::Html {
+        return f(ExportStatistics::Chat(html::gather(&input.file, options)?));
+    }
+    if streaming.stream {
+        let reader = io::BufReader::new(fs::File::open(&input.file).map_err(Error::io(&input.file))?);
+        let stat = stream::gather(reader, streaming.batch_size, &input.filter(), &schema, options)?;
+        check(&schema)?;
+        return f(stat);
+    }
+    let mapped = input.map()?;
+    let export = stream::collect(input.content(&mapped)?, &input.filter(), &schema)?;
+    check(&schema)?;
+    f(match &export {
+        Export::Chat(chat) => ExportStatistics::Chat(ChatStatistics::gather(chat, options)),
+        Export::Account(account) => ExportStatistics::Account(AccountStatistics::gather(account, options)),
+    })
+}
 
-    if let Some(jobs) = cli.jobs {
-        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global().unwrap();
+/// Fails on schema violations in strict mode, and warns about the skipped messages in lenient mode.
+fn check(schema: &Schema) -> Result<()> {
+    for violation in schema.check()? {
+        eprintln!("skipping {violation}");
     }
+    Ok(())
+}
 
-    let content = fs::read_to_string(cli.file)?;
+/// The messages of a chat selected by `Input`.
+struct Selected<'a> {
+    /// Only set for the chats of an account export.
+    name: Option<Cow<'a, str>>,
+    messages: Vec<Message<'a>>,
+}
 
-    let chat: Chat = serde_json::from_str(&content)?;
-    let stat: ChatStatistics = ChatStatistics::gather(&chat);
+/// Reads every message of the chats selected by `input` and hands them to `f`.
+fn load<T>(input: &Input, f: impl FnOnce(&[Selected]) -> Result<T>) -> Result<T> {
+    if input.format() == InputFormat::Html {
+        return f(&[Selected { name: None, messages: html::read(&input.file)? }]);
+    }
+    let schema = Schema::new(input.schema);
+    let mapped = input.map()?;
+    let export = stream::collect(input.content(&mapped)?, &input.filter(), &schema)?;
+    check(&schema)?;
+    let chats = match export {
+        Export::Chat(chat) => vec![Selected { name: None, messages: chat.messages }],
+        Export::Account(account) => account
+            .chats
+            .into_iter()
+            .chain(account.left_chats)
+            .map(|chat| Selected { name: Some(chat.name), messages: chat.messages })
+            .collect(),
+    };
+    f(&chats)
+}
 
-    let file = fs::File::create(cli.output)?;
-    serde_json::to_writer_pretty(file, &stat)?;
+/// Buffers standard output for `f`.
+fn print(f: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    f(&mut out).and_then(|()| out.flush()).map_err(Error::io("<stdout>"))
+}
 
+fn write_ranked(out: &mut dyn Write, ranked: &[Ranked]) -> io::Result<()> {
+    for ranked in ranked {
+        writeln!(out, "{:>4}  {:>8}  {:>6.2}%  {}", ranked.rank, ranked.count, ranked.share * 100.0, ranked.token.0)?;
+    }
     Ok(())
 }
 
+/// The current name of `member`, with its id if it has one.
+fn label(member: &Member, members: &Members) -> String {
+    let name = members.0.get(member).map(|names| names.name.as_ref());
+    match (member, name) {
+        (Member::Id(id), Some(name)) => format!("{name} ({id})"),
+        (Member::Id(id), None) => id.to_string(),
+        (Member::Name(name), _) => name.to_string(),
+    }
+}
+
+/// How the statistics were gathered, written out next to them.
+#[derive(Debug, Serialize)]
+struct Metadata {
+    tokenizer: TokenizerKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_pattern: Option<String>,
+    normalization: Normalization,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stem: Vec<Language>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lemmas: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stopwords_language: Vec<Language>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stopwords: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<Zone>,
+    #[serde(skip_serializing_if = "MessageFilter::is_empty")]
+    filter: MessageFilter,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    metadata: Metadata,
+    #[serde(flatten)]
+    statistics: ExportStatistics<ChatStatistics<'a>>,
+}
+
 #[derive(Debug, Parser)]
+#[command(about = "Statistics about Telegram Desktop exports")]
 struct Cli {
-    #[arg(long, short)]
-    file: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write every statistic to a JSON report
+    Stats(StatsArgs),
+    /// List the most used words
+    Top(TopArgs),
+    /// Summarize the activity of every member
+    Members(MembersArgs),
+    /// Find the messages matching a regular expression
+    Search(SearchArgs),
+    /// Count the messages and words sent over time, as CSV for plotting
+    Timeline(TimelineArgs),
+    /// Convert the messages to another format
+    Export(ExportArgs),
+}
+
+#[derive(Debug, Args)]
+struct StatsArgs {
+    #[command(flatten)]
+    input: Input,
+    #[command(flatten)]
+    streaming: Streaming,
+    #[command(flatten)]
+    analysis: Analysis,
     #[arg(long, short, default_value = "out.json")]
     output: PathBuf,
-    #[arg(long, short)]
-    jobs: Option<usize>
+    /// Also list the N most used words overall and of every member
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    top: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
-struct Person<'a>(Cow<'a, str>);
+#[derive(Debug, Args)]
+struct TopArgs {
+    #[command(flatten)]
+    input: Input,
+    #[command(flatten)]
+    streaming: Streaming,
+    #[command(flatten)]
+    analysis: Analysis,
+    /// Number of words to list
+    #[arg(short, default_value_t = 10, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    n: usize,
+    /// Also list the most used words of every member
+    #[arg(long)]
+    members: bool,
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
-struct Token<'a>(Cow<'a, str>);
+#[derive(Debug, Args)]
+struct MembersArgs {
+    #[command(flatten)]
+    input: Input,
+    #[command(flatten)]
+    streaming: Streaming,
+    #[command(flatten)]
+    analysis: Analysis,
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
 
-impl<'a> From<&'a str> for Token<'a> {
-    fn from(value: &'a str) -> Self {
-        Token(value.into())
-    }
+#[derive(Debug, Args)]
+struct SearchArgs {
+    #[command(flatten)]
+    input: Input,
+    /// Regular expression to look for in the text of the messages
+    pattern: String,
+    /// Match regardless of case
+    #[arg(long, short)]
+    ignore_case: bool,
+    /// How to print the messages found
+    #[arg(long, value_enum, default_value = "text")]
+    to: Format,
 }
-impl<'a> From<String> for Token<'a> {
-    fn from(value: String) -> Self {
-        Token(value.into())
-    }
+
+#[derive(Debug, Args)]
+struct TimelineArgs {
+    #[command(flatten)]
+    input: Input,
+    #[command(flatten)]
+    streaming: Streaming,
+    #[command(flatten)]
+    analysis: Analysis,
+    /// Length of the periods messages are counted over
+    #[arg(long, value_enum, default_value_t)]
+    by: Period,
+    /// Count messages by weekday and hour of the day instead
+    #[arg(long, conflicts_with = "by")]
+    heatmap: bool,
+    /// Also count the messages of every member, the rows of the whole chat have no member
+    #[arg(long)]
+    members: bool,
+    /// Print JSON with both the periods and the heatmap instead of CSV
+    #[arg(long)]
+    json: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
-struct Id(u128);
+#[derive(Debug, Args)]
+struct ExportArgs {
+    #[command(flatten)]
+    input: Input,
+    #[arg(long, value_enum, default_value_t)]
+    to: Format,
+    /// Standard output when omitted
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Chat<'a> {
-    name: String,
-    #[serde(rename = "type")]
-    chat_type: ChatType,
-    id: Id,
-    messages: Vec<Message<'a>>,
+/// Which export to read and which of its chats, shared by every command.
+#[derive(Debug, Args)]
+struct Input {
+    /// `result.json`, or the directory or a page of an HTML export
+    #[arg(long, short)]
+    file: PathBuf,
+    /// Number of threads, one per core when omitted
+    #[arg(long, short)]
+    jobs: Option<usize>,
+    /// Format of the export, guessed from `file` when omitted
+    #[arg(long, value_enum)]
+    format: Option<InputFormat>,
+    /// How to handle messages that don't match the export schema
+    #[arg(long, value_enum, default_value_t)]
+    schema: SchemaMode,
+    /// Only gather chats with this name from an account export
+    #[arg(long)]
+    chat: Vec<String>,
+    /// Only gather the chat with this id from an account export
+    #[arg(long)]
+    chat_id: Vec<u128>,
+    /// Only gather chats of this type from an account export
+    #[arg(long, value_enum)]
+    chat_type: Vec<ChatType>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-enum ChatType {
-    PublicChannel,
-    PrivateChannel,
-    PublicSupergroup,
-    PrivateSupergroup,
-    PersonalChat,
-    ChatForbidden,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Message<'a> {
-    id: u64,
-    #[serde(rename = "type")]
-    message_type: MessageType,
-    date: NaiveDateTime,
-    from: Option<Person<'a>>,
-    text_entities: Vec<TextEntity>,
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
-enum MessageType {
-    Service,
-    Message,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TextEntity {
-    #[serde(rename = "type")]
-    text_type: TextEntityType,
-    text: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
-#[serde(rename_all = "snake_case")]
-enum TextEntityType {
-    Pre,
-    Bold,
-    Link,
-    Code,
-    Email,
-    Plain,
-    Phone,
-    Italic,
-    Cashtag,
-    Spoiler,
-    Mention,
-    Hashtag,
-    TextLink,
-    Underline,
-    BotCommand,
-    CustomEmoji,
-    MentionName,
-    Strikethrough,
-}
-
-impl TextEntityType {
-    pub fn is_meta(&self) -> bool {
-        use TextEntityType::*;
-        matches!(self, Phone | BotCommand | Email | CustomEmoji | Mention)
+impl Input {
+    fn format(&self) -> InputFormat {
+        self.format.unwrap_or_else(|| InputFormat::detect(&self.file))
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ChatStatistics<'a> {
-    num_tokens: usize,
-    #[serde(borrow)]
-    members_tokens_map: HashMap<Person<'a>, HashMap<Token<'a>, usize>>,
-    #[serde(borrow)]
-    tokens_map: HashMap<Token<'a>, usize>,
-}
-impl<'a> ChatStatistics<'a> {
-    pub fn gather(chat: &'a Chat) -> Self {
-        let jobs = rayon::current_num_threads();
-
-        let num_messages = chat.messages.len();
-        let messages_per_thread = num_messages / jobs;
-
-        let (sender, receiver) = crossbeam::channel::bounded(jobs + 1);
-        let mut tokens_map = HashMap::new();
-        let mut members_tokens_map = HashMap::new();
-
-        chat.messages.par_iter().chunks(messages_per_thread).for_each_with(sender, move |sender, messages: Vec<&Message>| {
-            let mut chunk_tokens_map: HashMap<Token<'_>, usize> = HashMap::new();
-            let mut chunk_members_tokens_map: HashMap<Person<'_>, HashMap<Token<'_>, usize>> = HashMap::new();
-            for message in messages.iter().filter(|message| message.message_type != MessageType::Service) {
-                let from = message.from.clone().unwrap();
-                for entity in message.text_entities.iter().filter(|entity| !entity.text_type.is_meta()) {
-                    for token in entity.text.split(SEPARATORS).filter(|s| !s.is_empty()) {
-                        let token = Token::from(remove_emojis(token)); // <-- such a performance hit!
-                        *chunk_tokens_map.entry(token.clone()).or_insert(0) += 1;
-                        if let Some(map) =  chunk_members_tokens_map.get_mut(&from) {
-                            *map.entry(token).or_insert(0) += 1;
-                        } else  {
-                            let member_occurences_map = HashMap::from([(token, 1)]);
-                            chunk_members_tokens_map.insert(from.clone(), member_occurences_map);
-                        }
-                    } 
-                }
-            }
-            sender.send((chunk_tokens_map, chunk_members_tokens_map)).unwrap();
-        });
-        for _ in 0..jobs {
-            let (chunk_tokens_map, chunk_members_tokens_map): (HashMap<Token<'_>, usize>, _) = receiver.recv().unwrap();
-            merge_maps_with(&mut tokens_map, chunk_tokens_map, |tokens_map, token, occurences| *tokens_map.entry(token).or_insert(0) += occurences);
-            for (member, map) in chunk_members_tokens_map {
-                if let Some(mergee) = members_tokens_map.get_mut(&member) {
-                    merge_maps_with(mergee, map, |mergee, member, occurences| *mergee.entry(member).or_insert(0) += occurences);
-                } else {
-                    members_tokens_map.insert(member, map);
-                }
-            }
-        }
-        Self {
-            num_tokens: tokens_map.len(),
-            members_tokens_map,
-            tokens_map,
+    fn filter(&self) -> ChatFilter {
+        ChatFilter {
+            names: self.chat.clone(),
+            ids: self.chat_id.clone(),
+            types: self.chat_type.clone(),
         }
     }
-}
 
-fn remove_emojis(string: &str) -> String {
-    use unicode_segmentation::UnicodeSegmentation;
-    let graphemes = string.graphemes(true);
+    fn map(&self) -> Result<memmap2::Mmap> {
+        let file = fs::File::open(&self.file).map_err(Error::io(&self.file))?;
+        // SAFETY: the export must not be modified while it is read, which is the case for any export sitting on disk.
+        unsafe { memmap2::Mmap::map(&file) }.map_err(Error::io(&self.file))
+    }
+
+    fn content<'m>(&self, mapped: &'m memmap2::Mmap) -> Result<&'m str> {
+        std::str::from_utf8(mapped).map_err(|error| Error::Io { path: self.file.clone(), source: io::Error::new(io::ErrorKind::InvalidData, error) })
+    }
+}
 
-    let is_not_emoji = |x: &&str| emojis::get(x).is_none();
+/// For the commands that only need statistics, which can be gathered without loading the whole export.
+#[derive(Debug, Args)]
+struct Streaming {
+    /// Walk the `messages` array incrementally instead of loading the whole export.
+    ///
+    /// Memory then grows with the statistics rather than the export, save for the author of every message,
+    /// kept to credit replies to it: 20 to 40 bytes per message, up to 400 MB for 10 million messages.
+    #[arg(long)]
+    stream: bool,
+    /// Number of messages handed to the statistics at once in streaming mode
+    #[arg(long, default_value_t = 10_000, requires = "stream", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    batch_size: usize,
+}
 
-    graphemes.filter(is_not_emoji).collect()
+/// How text is split into the words that get counted.
+#[derive(Debug, Args)]
+struct Analysis {
+    /// How to split text into words
+    #[arg(long, value_enum, default_value_t)]
+    tokenizer: TokenizerKind,
+    /// Regular expression matching a single token, for `--tokenizer regex`
+    #[arg(long, required_if_eq("tokenizer", "regex"), value_parser = regex::Regex::new)]
+    token_pattern: Option<regex::Regex>,
+    /// Keep compatibility variants of characters (ligatures, full-width forms, ...) apart
+    #[arg(long)]
+    no_nfkc: bool,
+    /// Count differently cased words separately
+    #[arg(long)]
+    no_case_fold: bool,
+    /// Drop accents and other diacritics from words
+    #[arg(long)]
+    strip_diacritics: bool,
+    /// Count `ё` as `е`
+    #[arg(long)]
+    fold_yo: bool,
+    /// Leave out the bundled stopwords of these languages, e.g. `--stopwords-language uk,ru,en`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    stopwords_language: Vec<Language>,
+    /// Leave out the words listed in this file, one per line
+    #[arg(long)]
+    stopwords: Vec<PathBuf>,
+    /// Count words by their stem in these languages, e.g. `--stem uk,ru,en`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    stem: Vec<Language>,
+    /// Dictionary of `lemma form` lines to count words by their lemma, before stemming
+    #[arg(long)]
+    lemmas: Option<PathBuf>,
+    /// Also count sequences of N consecutive words, e.g. `--ngram 2,3` for bigrams and trigrams
+    #[arg(long, value_delimiter = ',', value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+    ngram: Vec<usize>,
+    /// Count dates and hours in this zone, an IANA name like `Europe/Kyiv` or an offset like `+02:00`,
+    /// instead of the local time of whoever exported the chat
+    #[arg(long, allow_hyphen_values = true)]
+    timezone: Option<Zone>,
+    /// Only analyze messages sent on or after this day, e.g. `2023-07-01`
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    /// Only analyze messages sent on or before this day
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    /// Only analyze messages of these members, by id like `user123` or by display name
+    #[arg(long)]
+    member: Vec<String>,
+    /// Leave out messages of these members
+    #[arg(long)]
+    exclude_member: Vec<String>,
 }
 
-fn merge_maps_with<K, F>(dst: &mut HashMap<K, usize>, src: HashMap<K, usize>, f: F) 
-where K: Eq + PartialEq + Hash,
-F: Fn(&mut HashMap<K, usize>, K, usize) {
-    for (key, occurences) in src {
-        f(dst, key, occurences)
+impl Analysis {
+    fn options(self) -> Result<(Metadata, Options)> {
+        let normalization = Normalization {
+            nfkc: !self.no_nfkc,
+            case_fold: !self.no_case_fold,
+            strip_diacritics: self.strip_diacritics,
+            fold_yo: self.fold_yo,
+        };
+        let filter = MessageFilter {
+            since: self.since,
+            until: self.until,
+            members: self.member,
+            exclude_members: self.exclude_member,
+        };
+        let metadata = Metadata {
+            tokenizer: self.tokenizer,
+            token_pattern: self.token_pattern.as_ref().map(|pattern| pattern.to_string()),
+            normalization,
+            stem: self.stem.clone(),
+            lemmas: self.lemmas.clone(),
+            stopwords_language: self.stopwords_language.clone(),
+            stopwords: self.stopwords.clone(),
+            timezone: self.timezone,
+            filter: filter.clone(),
+        };
+        let lemmas = match &self.lemmas {
+            Some(path) => morphology::load_lemmas(path, &normalization)?,
+            None => HashMap::new(),
+        };
+        let options = Options {
+            tokenizer: self.tokenizer.build(self.token_pattern)?,
+            normalization,
+            stopwords: Stopwords::load(&self.stopwords_language, &self.stopwords, &normalization)?,
+            morphology: Morphology::new(self.stem, lemmas),
+            ngrams: self.ngram,
+            zone: self.timezone.unwrap_or_default(),
+            filter,
+        };
+        Ok((metadata, options))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,160 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::model::{MemberId, Message, MessageType};
+
+/// Whom a message is counted towards.
+///
+/// The `from_id` when the export has one; HTML exports only carry display names, so there the name is all we have.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Member<'a> {
+    /// The `from_id` or `actor_id`.
+    Id(MemberId),
+    /// The display name, for lack of an id.
+    Name(Cow<'a, str>),
+}
+
+impl<'a> Member<'a> {
+    /// The author of `message`, if it has one at all.
+    pub fn of(message: &'a Message) -> Option<Self> {
+        match (message.from_id, &message.from) {
+            (Some(id), _) => Some(Member::Id(id)),
+            (None, Some(from)) => Some(Member::Name(Cow::Borrowed(&from.0))),
+            (None, None) => None,
+        }
+    }
+    /// The member a service message's `actor`/`actor_id` refers to.
+    pub fn actor(message: &'a Message) -> Option<Self> {
+        match (message.actor_id, &message.actor) {
+            (Some(id), _) => Some(Member::Id(id)),
+            (None, Some(actor)) => Some(Member::Name(Cow::Borrowed(actor))),
+            (None, None) => None,
+        }
+    }
+    /// Copies the name, if it is borrowed.
+    pub fn into_owned(self) -> Member<'static> {
+        match self {
+            Member::Id(id) => Member::Id(id),
+            Member::Name(name) => Member::Name(Cow::Owned(name.into_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Member<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Member::Id(id) => id.fmt(f),
+            Member::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl Serialize for Member<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Member::Id(id) => serializer.collect_str(id),
+            Member::Name(name) => serializer.serialize_str(name),
+        }
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Member<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let member = String::deserialize(deserializer)?;
+        Ok(member.parse().map(Member::Id).unwrap_or(Member::Name(Cow::Owned(member))))
+    }
+}
+
+/// The display names of every member.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Members<'a>(#[serde(borrow)] pub HashMap<Member<'a>, MemberNames<'a>>);
+
+impl<'a> Analyzer<'a> for Members<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        let message = observation.message;
+        if message.message_type == MessageType::Service {
+            return;
+        }
+        if let (Some(from), Some(name)) = (&observation.from, &message.from) {
+            match self.0.get_mut(from) {
+                Some(names) => names.observe(&name.0, observation.date),
+                None => {
+                    self.0.insert(from.clone(), MemberNames::new(&name.0, observation.date));
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (member, names) in other.0 {
+            if let Some(mergee) = self.0.get_mut(&member) {
+                mergee.merge(names);
+            } else {
+                self.0.insert(member, names);
+            }
+        }
+    }
+}
+
+impl Members<'_> {
+    /// Copies the members and names borrowed from the messages.
+    pub fn into_owned(self) -> Members<'static> {
+        Members(self.0.into_iter().map(|(member, names)| (member.into_owned(), names.into_owned())).collect())
+    }
+}
+
+/// The display names a member went by.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberNames<'a> {
+    /// The name of the member's most recent message.
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+    /// Every name, the current one included.
+    #[serde(borrow)]
+    pub names: BTreeSet<Cow<'a, str>>,
+    #[serde(skip)]
+    last_seen: NaiveDateTime,
+}
+
+impl<'a> MemberNames<'a> {
+    /// A member first seen as `name` at `date`.
+    pub fn new(name: &'a str, date: NaiveDateTime) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            names: BTreeSet::from([Cow::Borrowed(name)]),
+            last_seen: date,
+        }
+    }
+    /// Records `name`, which becomes the current one unless a later message was seen already.
+    pub fn observe(&mut self, name: &'a str, date: NaiveDateTime) {
+        if date >= self.last_seen {
+            self.name = Cow::Borrowed(name);
+            self.last_seen = date;
+        }
+        if !self.names.contains(name) {
+            self.names.insert(Cow::Borrowed(name));
+        }
+    }
+    /// Keeps the names of both, and the most recent one as the current one.
+    pub fn merge(&mut self, other: Self) {
+        if other.last_seen >= self.last_seen {
+            self.name = other.name;
+            self.last_seen = other.last_seen;
+        }
+        self.names.extend(other.names);
+    }
+    /// Copies the names borrowed from the messages.
+    pub fn into_owned(self) -> MemberNames<'static> {
+        MemberNames {
+            name: Cow::Owned(self.name.into_owned()),
+            names: self.names.into_iter().map(|name| Cow::Owned(name.into_owned())).collect(),
+            last_seen: self.last_seen,
+        }
+    }
+}
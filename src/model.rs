@@ -0,0 +1,611 @@
+//! The layout of Telegram's `result.json`, field for field.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A display name as the export wrote it.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
+pub struct Person<'a>(#[serde(borrow)] pub Cow<'a, str>);
+
+/// The `id` of a chat.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+pub struct Id(pub u128);
+
+/// Stable identity of a message author, written by Telegram as `user123456`, `channel789` or `chat42`.
+///
+/// Unlike the display name in `from`, it survives renames and tells apart members with the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MemberId {
+    /// A user, `user123456`.
+    User(u64),
+    /// A channel, `channel789`, e.g. posting in its discussion group.
+    Channel(u64),
+    /// A group, `chat42`, e.g. posting as its anonymous admins.
+    Chat(u64),
+}
+
+impl FromStr for MemberId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (constructor, id): (fn(u64) -> Self, _) = if let Some(id) = s.strip_prefix("user") {
+            (MemberId::User, id)
+        } else if let Some(id) = s.strip_prefix("channel") {
+            (MemberId::Channel, id)
+        } else if let Some(id) = s.strip_prefix("chat") {
+            (MemberId::Chat, id)
+        } else {
+            return Err(format!("`{s}` is not a user, channel or chat id"));
+        };
+        id.parse().map(constructor).map_err(|_| format!("`{s}` is not a user, channel or chat id"))
+    }
+}
+
+impl fmt::Display for MemberId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemberId::User(id) => write!(f, "user{id}"),
+            MemberId::Channel(id) => write!(f, "channel{id}"),
+            MemberId::Chat(id) => write!(f, "chat{id}"),
+        }
+    }
+}
+
+impl Serialize for MemberId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MemberId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Cow::<str>::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A single chat export, or one of the chats of an account export.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Chat<'a> {
+    /// Empty for chats of deleted accounts.
+    #[serde(borrow, default)]
+    pub name: Cow<'a, str>,
+    /// Personal chat, group, channel and so on.
+    #[serde(rename = "type")]
+    pub chat_type: ChatType,
+    /// The `id` Telegram gave the chat.
+    pub id: Id,
+    /// Every message, in the order they were sent.
+    pub messages: Vec<Message<'a>>,
+}
+
+/// The `type` of a chat.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum ChatType {
+    /// A channel with a public username
+    PublicChannel,
+    /// A channel joined by invite only
+    PrivateChannel,
+    /// A supergroup with a public username
+    PublicSupergroup,
+    /// A supergroup joined by invite only
+    PrivateSupergroup,
+    /// A basic group
+    PrivateGroup,
+    /// A chat with a single user
+    PersonalChat,
+    /// A chat with a bot
+    BotChat,
+    /// The account's own Saved Messages
+    SavedMessages,
+    /// Replies to the account's comments in channels
+    Replies,
+    /// Login codes sent by Telegram
+    VerificationCodes,
+    /// A chat the account can no longer access
+    ChatForbidden,
+    /// Anything newer than this model, kept verbatim.
+    #[cfg_attr(feature = "cli", value(skip))]
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// A message as written to `result.json`.
+///
+/// Media, service and reply fields are only present on the messages they apply to. Anything
+/// Telegram adds that isn't modelled here ends up in `extra` instead of being dropped.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Message<'a> {
+    /// Unique within the chat, increasing with time.
+    pub id: u64,
+    /// Whether a member wrote it or Telegram logged an action.
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    /// When it was sent, in the local time of whoever exported the chat.
+    pub date: NaiveDateTime,
+    /// `date` as a Unix time, missing from older exports.
+    #[serde(default, deserialize_with = "unixtime", skip_serializing_if = "Option::is_none")]
+    pub date_unixtime: Option<i64>,
+    /// When it was last edited, in the same local time as `date`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edited: Option<NaiveDateTime>,
+    /// `edited` as a Unix time.
+    #[serde(default, deserialize_with = "unixtime", skip_serializing_if = "Option::is_none")]
+    pub edited_unixtime: Option<i64>,
+    /// Display name of the author when the chat was exported, `None` for deleted accounts.
+    #[serde(borrow)]
+    pub from: Option<Person<'a>>,
+    /// Who wrote it, `None` on service messages and unsigned channel posts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_id: Option<MemberId>,
+    /// Signature of a channel post.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Name of whoever the forwarded message was originally from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<String>,
+    /// Name of the chat a message in Saved Messages was saved from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_from: Option<String>,
+    /// The message it replies to, in the same chat unless `reply_to_peer_id` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to_message_id: Option<u64>,
+    /// The chat of the message it replies to, if that is another chat.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to_peer_id: Option<String>,
+    /// Username of the inline bot it was sent via, e.g. `@gif`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via_bot: Option<String>,
+
+    /// What kind of file `file` is; photos have `photo` instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<MediaType>,
+    /// Path of the photo within the export.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photo: Option<String>,
+    /// Path of the file within the export, or why it wasn't exported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Name of the file as it was sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// Path of the thumbnail of `file` within the export.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// MIME type of `file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Width of a photo or video, in pixels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Height of a photo or video, in pixels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Length of audio and video media, or of a `phone_call`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u64>,
+    /// The emoji a sticker stands for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sticker_emoji: Option<String>,
+    /// Performer of an audio file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub performer: Option<String>,
+    /// Title of an audio file, or the new title of an `edit_group_title`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Reactions to it, one entry per kind.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<Reaction>,
+    /// The poll it carries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll: Option<Poll>,
+    /// The location it shares.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_information: Option<Location>,
+    /// The contact it shares.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_information: Option<ContactInformation>,
+
+    /// What a service message logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+    /// Display name of whoever did what a service message logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// Who did what a service message logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<MemberId>,
+    /// Display names of the members an `invite_members`/`remove_members` applies to,
+    /// `None` for deleted accounts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<Option<String>>,
+    /// Who invited the members of an `invite_members`, if it wasn't the actor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inviter: Option<String>,
+    /// The message a `pin_message` pins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<u64>,
+    /// Why a `phone_call` ended, e.g. `missed` or `hangup`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discard_reason: Option<String>,
+
+    /// The text, split into runs of a single kind of formatting.
+    #[serde(borrow)]
+    pub text_entities: Vec<TextEntity<'a>>,
+    /// Fields this model doesn't know about, kept as they were.
+    #[serde(flatten, deserialize_with = "extra")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Message<'_> {
+    /// The text of every entity, as the message reads.
+    pub fn text(&self) -> Cow<'_, str> {
+        match self.text_entities.as_slice() {
+            [] => Cow::Borrowed(""),
+            [entity] => Cow::Borrowed(&entity.text),
+            entities => Cow::Owned(entities.iter().map(|entity| entity.text.as_ref()).collect()),
+        }
+    }
+}
+
+/// Whether a member wrote the message or Telegram logged an action.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageType {
+    /// Logged by Telegram, see `Message::action`.
+    Service,
+    /// Written by a member.
+    #[default]
+    Message,
+}
+
+/// What kind of file a message carries.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaType {
+    /// A sticker, static or animated.
+    Sticker,
+    /// A GIF, or a silent video sent as one.
+    Animation,
+    /// Music or any other audio sent as a file.
+    AudioFile,
+    /// A video sent as a file.
+    VideoFile,
+    /// A round video message.
+    VideoMessage,
+    /// A voice message.
+    VoiceMessage,
+    /// Anything newer than this model, kept verbatim.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// What a service message logs.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// A basic group was created.
+    CreateGroup,
+    /// The channel was created.
+    CreateChannel,
+    /// The basic group became a supergroup, logged in the old group.
+    MigrateToSupergroup,
+    /// The supergroup was a basic group, logged in the new supergroup.
+    MigrateFromGroup,
+    /// Members were added, see `Message::members`.
+    InviteMembers,
+    /// Members were removed or left, see `Message::members`.
+    RemoveMembers,
+    /// The actor joined through an invite link.
+    JoinGroupByLink,
+    /// The actor's request to join was approved.
+    JoinGroupByRequest,
+    /// A message was pinned, see `Message::message_id`.
+    PinMessage,
+    /// The chat was renamed, see `Message::title`.
+    EditGroupTitle,
+    /// The chat photo was changed, see `Message::photo`.
+    EditGroupPhoto,
+    /// The chat photo was removed.
+    DeleteGroupPhoto,
+    /// A call in a personal chat.
+    PhoneCall,
+    /// A video chat was started.
+    GroupCall,
+    /// Members were invited to a video chat.
+    InviteToGroupCall,
+    /// A video chat was scheduled.
+    GroupCallScheduled,
+    /// A game score was set.
+    ScoreInGame,
+    /// The history of a personal chat was cleared.
+    ClearHistory,
+    /// Auto-deletion of messages was set or turned off.
+    SetMessagesTtl,
+    /// The chat theme was changed.
+    EditChatTheme,
+    /// A forum topic was created.
+    TopicCreated,
+    /// A forum topic was renamed, closed or reopened.
+    TopicEdit,
+    /// Anything newer than this model, kept verbatim.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Reactions of one kind to a message.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reaction {
+    /// Whether it is an emoji, a custom emoji or paid.
+    #[serde(rename = "type")]
+    pub reaction_type: ReactionType,
+    /// How many members reacted this way.
+    pub count: u64,
+    /// The emoji of an `emoji` reaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// The sticker document of a `custom_emoji` reaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<String>,
+    /// The last few members who reacted this way.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent: Vec<RecentReaction>,
+}
+
+/// A plain emoji, a custom one or a paid star.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionType {
+    /// A standard emoji.
+    Emoji,
+    /// A custom emoji of a sticker set.
+    CustomEmoji,
+    /// A Telegram Stars reaction.
+    Paid,
+    /// Anything newer than this model, kept verbatim.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// One of the last members to react.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecentReaction {
+    /// Display name of the member.
+    pub from: Option<String>,
+    /// Who reacted.
+    pub from_id: Option<MemberId>,
+    /// When they reacted.
+    pub date: Option<NaiveDateTime>,
+}
+
+/// A poll and its results.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Poll {
+    /// What was asked.
+    pub question: String,
+    /// Whether voting has ended.
+    #[serde(default)]
+    pub closed: bool,
+    /// Members who voted.
+    #[serde(default)]
+    pub total_voters: u64,
+    /// The options, in order.
+    #[serde(default)]
+    pub answers: Vec<PollAnswer>,
+}
+
+/// One of the options of a poll.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollAnswer {
+    /// The option itself.
+    pub text: String,
+    /// Members who chose it.
+    #[serde(default)]
+    pub voters: u64,
+    /// Whether the account that exported the chat chose it.
+    #[serde(default)]
+    pub chosen: bool,
+}
+
+/// A shared location.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Location {
+    /// In degrees, north positive.
+    pub latitude: f64,
+    /// In degrees, east positive.
+    pub longitude: f64,
+}
+
+/// A shared contact.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContactInformation {
+    /// As saved in the contact.
+    #[serde(default)]
+    pub first_name: String,
+    /// As saved in the contact.
+    #[serde(default)]
+    pub last_name: String,
+    /// As saved in the contact.
+    #[serde(default)]
+    pub phone_number: String,
+}
+
+/// A run of text with a single kind of formatting.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TextEntity<'a> {
+    /// The formatting.
+    #[serde(rename = "type")]
+    pub text_type: TextEntityType,
+    /// The text itself.
+    #[serde(borrow)]
+    pub text: Cow<'a, str>,
+    /// Target of a `text_link`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    /// Mentioned user of a `mention_name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<u64>,
+    /// Sticker document of a `custom_emoji`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<String>,
+    /// Language of a `pre` block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// The formatting of a text entity.
+#[derive(Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEntityType {
+    /// A preformatted block.
+    Pre,
+    /// Bold text.
+    Bold,
+    /// A URL written out in the text.
+    Link,
+    /// Inline code.
+    Code,
+    /// An email address.
+    Email,
+    /// Unformatted text.
+    #[default]
+    Plain,
+    /// A phone number.
+    Phone,
+    /// Italic text.
+    Italic,
+    /// A ticker like `$TON`.
+    Cashtag,
+    /// Text hidden until tapped.
+    Spoiler,
+    /// A `@username`.
+    Mention,
+    /// A `#hashtag`.
+    Hashtag,
+    /// Text linking to `TextEntity::href`.
+    TextLink,
+    /// Underlined text.
+    Underline,
+    /// A `/command`.
+    BotCommand,
+    /// A custom emoji, see `TextEntity::document_id`.
+    CustomEmoji,
+    /// A mention of a user without a username, see `TextEntity::user_id`.
+    MentionName,
+    /// Struck out text.
+    Strikethrough,
+    /// Types Telegram added after this model, e.g. `blockquote` or `bank_card`.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl TextEntityType {
+    /// Whether the entity is a phone, email, mention, bot command or custom emoji, whose text isn't counted.
+    pub fn is_meta(&self) -> bool {
+        use TextEntityType::*;
+        matches!(self, Phone | BotCommand | Email | CustomEmoji | Mention)
+    }
+}
+
+/// Telegram writes unix times as strings, e.g. `"date_unixtime": "1672531200"`.
+fn unixtime<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Unixtime<'a> {
+        Number(i64),
+        String(#[serde(borrow)] Cow<'a, str>),
+    }
+
+    match Option::<Unixtime>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Unixtime::Number(time)) => Ok(Some(time)),
+        Some(Unixtime::String(time)) => time.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Keeps the fields `Message` doesn't model, except `text`: it is only `text_entities` flattened
+/// into a string or a mixed array, so keeping it would double the size of every message.
+fn extra<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, serde_json::Value>, D::Error> {
+    struct Extra;
+
+    impl<'de> serde::de::Visitor<'de> for Extra {
+        type Value = HashMap<String, serde_json::Value>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("the fields of a message")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut extra = HashMap::new();
+            while let Some(key) = map.next_key::<Cow<str>>()? {
+                // Skipped rather than removed afterwards, building its `Value` is most of the cost.
+                if key == "text" {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                } else {
+                    extra.insert(key.into_owned(), map.next_value()?);
+                }
+            }
+            Ok(extra)
+        }
+    }
+
+    deserializer.deserialize_map(Extra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_member_ids() {
+        assert_eq!("user123456".parse(), Ok(MemberId::User(123456)));
+        assert_eq!("channel789".parse(), Ok(MemberId::Channel(789)));
+        assert_eq!("chat42".parse(), Ok(MemberId::Chat(42)));
+        for id in ["user123456", "channel789", "chat42"] {
+            assert_eq!(id.parse::<MemberId>().unwrap().to_string(), id);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_member_ids() {
+        for id in ["", "user", "user-1", "user 1", "User1", "bot1", "123", "chat1a", "channel18446744073709551616"] {
+            assert!(id.parse::<MemberId>().is_err(), "{id}");
+        }
+    }
+
+    #[test]
+    fn keeps_unknown_types() {
+        let entity: TextEntity = serde_json::from_str(r#"{"type": "blockquote_v2", "text": "quoted"}"#).unwrap();
+        assert_eq!(entity.text_type, TextEntityType::Unknown("blockquote_v2".to_string()));
+        assert_eq!(entity.text, "quoted");
+        assert_eq!(serde_json::from_str::<TextEntityType>(r#""bold""#).unwrap(), TextEntityType::Bold);
+
+        assert_eq!(serde_json::from_str::<MediaType>(r#""hologram""#).unwrap(), MediaType::Unknown("hologram".to_string()));
+        assert_eq!(serde_json::from_str::<Action>(r#""teleport""#).unwrap(), Action::Unknown("teleport".to_string()));
+        assert_eq!(serde_json::from_str::<ReactionType>(r#""sparkles""#).unwrap(), ReactionType::Unknown("sparkles".to_string()));
+    }
+
+    #[test]
+    fn keeps_unknown_chat_types() {
+        assert_eq!(serde_json::from_str::<ChatType>(r#""replies""#).unwrap(), ChatType::Replies);
+        assert_eq!(serde_json::from_str::<ChatType>(r#""verification_codes""#).unwrap(), ChatType::VerificationCodes);
+        assert_eq!(serde_json::from_str::<ChatType>(r#""something_new""#).unwrap(), ChatType::Unknown("something_new".to_string()));
+        assert_eq!(serde_json::to_string(&ChatType::Unknown("something_new".to_string())).unwrap(), r#""something_new""#);
+
+        let chat: Chat = serde_json::from_str(r#"{"name": "Replies", "type": "replies", "id": 7, "messages": []}"#).unwrap();
+        assert_eq!(chat.chat_type, ChatType::Replies);
+    }
+
+    #[test]
+    fn deserializes_member_ids_from_strings() {
+        let message: Message = serde_json::from_str(r#"{"id": 1, "type": "message", "date": "2023-01-12T15:04:05", "from_id": "user7", "text_entities": []}"#).unwrap();
+        assert_eq!(message.from_id, Some(MemberId::User(7)));
+        assert!(serde_json::from_str::<Message>(r#"{"id": 1, "type": "message", "date": "2023-01-12T15:04:05", "from_id": "peer7", "text_entities": []}"#).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::Serialize;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::normalize::Normalization;
+
+/// A language with stopwords and a stemmer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// English
+    En,
+    /// Russian
+    Ru,
+    /// Ukrainian
+    Uk,
+}
+
+/// Maps inflected words to a canonical form: their lemma if the dictionary has one, their stem otherwise.
+#[derive(Debug, Default)]
+pub struct Morphology {
+    /// Tried in this order when the script of a word doesn't settle its language.
+    stemmers: Vec<Language>,
+    /// Form -> lemma.
+    lemmas: HashMap<String, String>,
+}
+
+impl Morphology {
+    /// Stems with `stemmers`, after looking words up in the `form -> lemma` dictionary.
+    pub fn new(stemmers: Vec<Language>, lemmas: HashMap<String, String>) -> Self {
+        Self { stemmers, lemmas }
+    }
+
+    /// Whether any word would be changed at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.stemmers.is_empty() || !self.lemmas.is_empty()
+    }
+
+    /// The canonical form of an already normalized `token`.
+    pub fn canonical<'t>(&self, token: &Cow<'t, str>) -> Cow<'t, str> {
+        if let Some(lemma) = self.lemmas.get(token.as_ref()) {
+            return Cow::Owned(lemma.clone());
+        }
+        let Some(language) = self.language(token) else {
+            return token.clone();
+        };
+        let stem = match language {
+            Language::En => Stemmer::create(Algorithm::English).stem(token),
+            Language::Ru => Stemmer::create(Algorithm::Russian).stem(token),
+            Language::Uk => Cow::Borrowed(stem_ukrainian(token)),
+        };
+        if stem == token.as_ref() {
+            token.clone()
+        } else {
+            Cow::Owned(stem.into_owned())
+        }
+    }
+
+    fn language(&self, token: &str) -> Option<Language> {
+        let enabled = |language: &Language| self.stemmers.contains(language);
+        if token.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Some(Language::En).filter(enabled);
+        }
+        if !token.chars().all(|c| matches!(c, 'а'..='я' | 'ё' | 'і' | 'ї' | 'є' | 'ґ' | '\'' | '’')) {
+            return None;
+        }
+        if token.contains(['і', 'ї', 'є', 'ґ']) {
+            Some(Language::Uk).filter(enabled)
+        } else if token.contains(['ы', 'э', 'ъ', 'ё']) {
+            Some(Language::Ru).filter(enabled)
+        } else {
+            self.stemmers.iter().copied().find(|language| *language != Language::En)
+        }
+    }
+}
+
+/// Reads a dictionary of `lemma<whitespace>form` lines, `#` starts a comment.
+/// Both sides are normalized like tokens are, otherwise they would never match.
+pub fn load_lemmas(path: &Path, normalization: &Normalization) -> Result<HashMap<String, String>> {
+    let mut lemmas = HashMap::new();
+    for (number, line) in fs::read_to_string(path).map_err(Error::io(path))?.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let (Some(lemma), Some(form), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(Error::Lemmas { path: path.to_path_buf(), line: number + 1, text: line.to_string() });
+        };
+        let normalize = |word: &str| normalization.apply(Cow::Borrowed(word)).into_owned();
+        lemmas.insert(normalize(form), normalize(lemma));
+    }
+    Ok(lemmas)
+}
+
+const UK_VOWELS: [char; 10] = ['а', 'е', 'и', 'о', 'у', 'ю', 'я', 'і', 'ї', 'є'];
+const PERFECTIVE_GERUND: &[&str] = &["ивши", "ившись", "вши", "вшись", "учи", "ючи", "ачи", "ячи", "учись", "ючись"];
+const REFLEXIVE: &[&str] = &["ся", "сь", "си"];
+// Single vowel endings are left to the noun step, they would take the verb ones out first.
+const ADJECTIVE: &[&str] = &["ими", "ій", "ий", "ова", "ове", "ів", "їй", "єє", "еє", "ім", "ем", "им", "их", "іх", "ою", "йми", "іми", "ого", "ому", "ої"];
+const PARTICIPLE: &[&str] = &["ущ", "ющ", "ащ", "ящ", "ан", "ян", "ен", "єн"];
+// Past tense endings keep their vowel, a bare `ла` would take it off nouns like `школа` too.
+const VERB: &[&str] = &[
+    "ив", "ил", "ать", "ять", "ав", "али", "ала", "ало", "ила", "ило", "или", "іла", "іло", "іли", "яла", "яло", "яли", "ула", "уло", "ули", "ати",
+    "яти", "ти", "ть", "ме", "атиме", "итиме", "атимуть", "итимуть", "атимеш", "итимеш", "атимемо", "итимемо", "атимете", "итимете", "ють", "ують",
+    "уть", "ить", "іть", "їть", "имо", "емо", "ете", "ите", "еш", "иш", "їш", "їмо", "їте", "аю", "аєш", "ає", "аємо", "аєте", "ають", "ай", "айте",
+    "яю", "яєш", "яє", "яємо", "яєте", "яють",
+];
+const NOUN: &[&str] = &[
+    "а", "ев", "ов", "е", "ями", "ами", "еи", "и", "ей", "ой", "ий", "й", "иям", "ям", "ием", "ем", "ам", "ом", "о", "у", "ах", "иях", "ях", "ь", "ию",
+    "ью", "ю", "ия", "ья", "я", "і", "ові", "ї", "ею", "єю", "ою", "є", "еві", "єм", "ів", "їв",
+];
+
+/// Snowball has no Ukrainian stemmer, this strips endings the way its Russian one does.
+fn stem_ukrainian(word: &str) -> &str {
+    // Endings are only looked for after the first vowel.
+    let Some(start) = word.char_indices().find(|(_, c)| UK_VOWELS.contains(c)).map(|(i, c)| i + c.len_utf8()) else {
+        return word;
+    };
+    let (head, mut rv) = word.split_at(start);
+    if let Some(rest) = strip_longest(rv, PERFECTIVE_GERUND) {
+        rv = rest;
+    } else {
+        rv = strip_longest(rv, REFLEXIVE).unwrap_or(rv);
+        if let Some(rest) = strip_longest(rv, ADJECTIVE) {
+            rv = strip_longest(rest, PARTICIPLE).unwrap_or(rest);
+        } else if let Some(rest) = strip_longest(rv, VERB) {
+            rv = rest;
+        } else {
+            rv = strip_longest(rv, NOUN).unwrap_or(rv);
+        }
+    }
+    rv = rv.strip_suffix('и').unwrap_or(rv);
+    rv = strip_longest(rv, &["ейш", "ейше"]).unwrap_or(rv);
+    if rv.ends_with("нн") {
+        rv = &rv[..rv.len() - 'н'.len_utf8()];
+    }
+    rv = rv.strip_suffix('ь').unwrap_or(rv);
+    &word[..head.len() + rv.len()]
+}
+
+fn strip_longest<'w>(word: &'w str, suffixes: &[&str]) -> Option<&'w str> {
+    suffixes.iter().filter_map(|suffix| word.strip_suffix(suffix)).min_by_key(|rest| rest.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_stems(stem: &str, forms: &[&str]) {
+        for form in forms {
+            assert_eq!(stem_ukrainian(form), stem, "{form}");
+        }
+    }
+
+    #[test]
+    fn stems_first_conjugation_verbs() {
+        assert_stems(
+            "чит",
+            &[
+                "читати", "читаю", "читаєш", "читає", "читаємо", "читаєте", "читають", "читав", "читала", "читало", "читали", "читай", "читайте",
+                "читатиме", "читатимуть",
+            ],
+        );
+    }
+
+    #[test]
+    fn stems_second_conjugation_verbs() {
+        assert_stems(
+            "говор",
+            &["говорити", "говорю", "говориш", "говорить", "говоримо", "говорите", "говорять", "говорив", "говорила", "говорили", "говори"],
+        );
+    }
+
+    #[test]
+    fn stems_adjectives() {
+        assert_stems("нов", &["новий", "нова", "нове", "нові", "нового", "новому", "новим", "новій", "нову", "новою", "нової", "нових", "новими"]);
+        assert_stems("син", &["синій", "синя", "синє", "сині", "синього", "синьому", "синім", "синю", "синьою", "синьої", "синіх", "синіми"]);
+    }
+
+    #[test]
+    fn stems_nouns() {
+        assert_stems("школ", &["школа", "школи", "школі", "школу", "школою", "школам", "школами", "школах"]);
+        assert_stems("пісн", &["пісня", "пісні", "пісню", "піснею", "пісням", "піснями", "піснях"]);
+        assert_stems("народ", &["народ", "народу", "народові", "народом", "народі", "народе", "народи", "народів", "народам", "народами", "народах"]);
+    }
+
+    #[test]
+    fn keeps_words_without_vowels() {
+        assert_eq!(stem_ukrainian("вжж"), "вжж");
+    }
+}
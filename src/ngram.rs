@@ -0,0 +1,181 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::member::Member;
+use crate::{merge_maps_with, Token};
+
+/// `n` adjacent tokens of the same text entity, written out space-separated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ngram<'a>(Box<[Token<'a>]>);
+
+impl Ngram<'_> {
+    /// Copies the tokens borrowed from the messages.
+    pub fn into_owned(self) -> Ngram<'static> {
+        Ngram(self.0.into_vec().into_iter().map(Token::into_owned).collect())
+    }
+}
+
+impl fmt::Display for Ngram<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, token) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(&token.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Ngram<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Ngram<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ngram = String::deserialize(deserializer)?;
+        Ok(Ngram(ngram.split(' ').map(|token| Token::from(token.to_string())).collect()))
+    }
+}
+
+/// N-gram statistics for every `n` in `Options::ngrams`.
+#[derive(Debug, Default, Clone, Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Ngrams<'a>(#[serde(borrow)] pub BTreeMap<usize, NgramStatistics<'a>>);
+
+impl<'a> Analyzer<'a> for Ngrams<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        for entity in &observation.entities {
+            for &n in observation.options.ngrams.iter().filter(|&&n| n >= 2) {
+                for run in entity.runs() {
+                    self.0.entry(n).or_default().observe(n, observation.from.as_ref(), run);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (n, ngrams) in other.0 {
+            self.0.entry(n).or_default().merge(ngrams);
+        }
+    }
+}
+
+impl Ngrams<'_> {
+    /// Whether no n-grams were asked for.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Copies the n-grams borrowed from the messages.
+    pub fn into_owned(self) -> Ngrams<'static> {
+        Ngrams(self.0.into_iter().map(|(n, ngrams)| (n, ngrams.into_owned())).collect())
+    }
+}
+
+/// Occurrences of every n-gram for a single `n`, overall and per member.
+#[derive(Debug, Default, Clone, Serialize, serde::Deserialize)]
+pub struct NgramStatistics<'a> {
+    /// Distinct n-grams.
+    pub num_ngrams: usize,
+    /// `ngrams_map` of every member.
+    #[serde(borrow)]
+    pub members_ngrams_map: HashMap<Member<'a>, HashMap<Ngram<'a>, usize>>,
+    /// Occurrences of every n-gram.
+    #[serde(borrow)]
+    pub ngrams_map: HashMap<Ngram<'a>, usize>,
+}
+
+impl<'a> NgramStatistics<'a> {
+    /// Counts the n-grams of adjacent `tokens` of one entity, so that they never span a stopword, an emoji, two entities or messages.
+    pub fn observe(&mut self, n: usize, member: Option<&Member<'a>>, tokens: &[Token<'a>]) {
+        for window in tokens.windows(n) {
+            let ngram = Ngram(window.into());
+            if let Some(member) = member {
+                *self.members_ngrams_map.entry(member.clone()).or_default().entry(ngram.clone()).or_insert(0) += 1;
+            }
+            *self.ngrams_map.entry(ngram).or_insert(0) += 1;
+        }
+        self.num_ngrams = self.ngrams_map.len();
+    }
+    /// Adds up the occurrences of both.
+    pub fn merge(&mut self, other: Self) {
+        merge_maps_with(&mut self.ngrams_map, other.ngrams_map, |ngrams_map, ngram, occurences| *ngrams_map.entry(ngram).or_insert(0) += occurences);
+        for (member, map) in other.members_ngrams_map {
+            if let Some(mergee) = self.members_ngrams_map.get_mut(&member) {
+                merge_maps_with(mergee, map, |mergee, ngram, occurences| *mergee.entry(ngram).or_insert(0) += occurences);
+            } else {
+                self.members_ngrams_map.insert(member, map);
+            }
+        }
+        self.num_ngrams = self.ngrams_map.len();
+    }
+    /// Copies the n-grams and members borrowed from the messages.
+    pub fn into_owned(self) -> NgramStatistics<'static> {
+        let owned_ngrams = |map: HashMap<Ngram<'a>, usize>| map.into_iter().map(|(ngram, occurences)| (ngram.into_owned(), occurences)).collect();
+        NgramStatistics {
+            num_ngrams: self.num_ngrams,
+            members_ngrams_map: self.members_ngrams_map.into_iter().map(|(member, map)| (member.into_owned(), owned_ngrams(map))).collect(),
+            ngrams_map: owned_ngrams(self.ngrams_map),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::borrow::Cow;
+
+    use crate::analyzer::analyze;
+    use crate::model::{MemberId, Message, TextEntity, TextEntityType};
+    use crate::morphology::Language;
+    use crate::normalize::Normalization;
+    use crate::stopwords::Stopwords;
+    use crate::Options;
+
+    fn message(id: u64, entities: &[&str]) -> Message<'static> {
+        Message {
+            id,
+            date: NaiveDate::from_ymd_opt(2023, 1, 12).unwrap().and_hms_opt(15, 4, 5).unwrap(),
+            from_id: Some(MemberId::User(1)),
+            text_entities: entities.iter().map(|text| TextEntity { text_type: TextEntityType::Plain, text: Cow::Owned(text.to_string()), ..Default::default() }).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn bigrams(messages: &[Message], options: &Options) -> Vec<String> {
+        let ngrams: Ngrams = analyze(messages, options);
+        let mut bigrams: Vec<String> = ngrams.0.get(&2).map(|stat| stat.ngrams_map.keys().map(Ngram::to_string).collect()).unwrap_or_default();
+        bigrams.sort();
+        bigrams
+    }
+
+    #[test]
+    fn stops_at_entities_and_messages() {
+        let messages = [message(1, &["quick brown ", "fox"]), message(2, &["jumps over"])];
+        let options = Options { ngrams: vec![2], ..Default::default() };
+        assert_eq!(bigrams(&messages, &options), ["jumps over", "quick brown"]);
+    }
+
+    #[test]
+    fn stops_at_stopwords() {
+        let messages = [message(1, &["dogs chase the cats at night"])];
+        let options = Options { ngrams: vec![2], stopwords: Stopwords::load(&[Language::En], &[], &Normalization::default()).unwrap(), ..Default::default() };
+        assert_eq!(bigrams(&messages, &options), ["dogs chase"]);
+    }
+
+    #[test]
+    fn skips_n_below_two() {
+        let messages = [message(1, &["quick brown fox"])];
+        let options = Options { ngrams: vec![0, 1, 3], ..Default::default() };
+        let ngrams: Ngrams = analyze(&messages, &options);
+        assert_eq!(ngrams.0.keys().copied().collect::<Vec<_>>(), [3]);
+        assert_eq!(ngrams.0[&3].num_ngrams, 1);
+    }
+}
@@ -0,0 +1,53 @@
+use caseless::Caseless;
+use serde::Serialize;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+use std::borrow::Cow;
+
+/// How tokens are folded before they are counted, the steps apply in the order of the fields.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Normalization {
+    /// Compatibility composition, so decomposed letters, ligatures and full-width forms count as the usual ones.
+    pub nfkc: bool,
+    /// Full Unicode case folding: `Straße` and `STRASSE` both become `strasse`.
+    pub case_fold: bool,
+    /// Drops accents and other combining marks: `café` becomes `cafe`, but `й` becomes `и` as well.
+    pub strip_diacritics: bool,
+    /// `ё` becomes `е`, which most Russian writers use interchangeably.
+    pub fold_yo: bool,
+}
+
+/// NFKC and case folding, the other steps lose information.
+impl Default for Normalization {
+    fn default() -> Self {
+        Self { nfkc: true, case_fold: true, strip_diacritics: false, fold_yo: false }
+    }
+}
+
+impl Normalization {
+    /// Leaves `token` borrowed unless some step actually changes it.
+    pub fn apply<'t>(&self, mut token: Cow<'t, str>) -> Cow<'t, str> {
+        if self.nfkc && is_nfkc_quick(token.chars()) != IsNormalized::Yes {
+            token = Cow::Owned(token.nfkc().collect());
+        }
+        if self.case_fold && !is_case_folded(&token) {
+            token = Cow::Owned(caseless::default_case_fold_str(&token));
+        }
+        if self.strip_diacritics && !token.is_ascii() && token.nfd().any(is_combining_mark) {
+            token = Cow::Owned(token.nfd().filter(|&c| !is_combining_mark(c)).nfc().collect());
+        }
+        if self.fold_yo && token.contains(['ё', 'Ё']) {
+            token = Cow::Owned(token.replace('ё', "е").replace('Ё', "Е"));
+        }
+        token
+    }
+}
+
+fn is_case_folded(token: &str) -> bool {
+    if token.is_ascii() {
+        !token.bytes().any(|byte| byte.is_ascii_uppercase())
+    } else {
+        token.chars().default_case_fold().eq(token.chars())
+    }
+}
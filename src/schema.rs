@@ -0,0 +1,178 @@
+use serde::Deserialize;
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+
+use crate::model::Message;
+
+/// What to do with messages that don't match the `Message` model.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SchemaMode {
+    /// Report every violation and fail
+    #[default]
+    Strict,
+    /// Skip the offending messages and warn about each of them
+    Lenient,
+}
+
+/// A message that failed to deserialize.
+#[derive(Debug)]
+pub struct SchemaViolation {
+    /// The `id` of the message, if it could be read at all.
+    pub message_id: Option<u64>,
+    /// JSON path of the offending value, e.g. `messages[12].text_entities[0].type`.
+    pub path: String,
+    /// What didn't match.
+    pub error: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message_id {
+            Some(id) => write!(f, "message {id} at {}: {}", self.path, self.error),
+            None => write!(f, "message at {}: {}", self.path, self.error),
+        }
+    }
+}
+
+/// Every violation of a strict `Schema`.
+#[derive(Debug)]
+pub struct SchemaError(pub Vec<SchemaViolation>);
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} message(s) don't match the export schema", self.0.len())?;
+        for violation in &self.0 {
+            write!(f, "\n  {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SchemaError {}
+
+/// Deserializes messages one at a time, so a single malformed message can be reported precisely.
+#[derive(Debug, Default)]
+pub struct Schema {
+    mode: SchemaMode,
+    violations: RefCell<Vec<SchemaViolation>>,
+}
+
+impl Schema {
+    /// A schema with no violations seen yet.
+    pub fn new(mode: SchemaMode) -> Self {
+        Self { mode, violations: RefCell::default() }
+    }
+
+    /// Deserializes the message at `path`, returning `None` if it violates the schema.
+    ///
+    /// The message borrows whatever strings of `raw` have no escapes.
+    pub fn message<'r>(&self, raw: &'r str, path: impl FnOnce() -> String) -> Option<Message<'r>> {
+        if let Ok(message) = Message::deserialize(&mut serde_json::Deserializer::from_str(raw)) {
+            return Some(message);
+        }
+        // Tracking the path costs an allocation per key, so only failing messages are read again to find it.
+        let error = match serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(raw)) {
+            Ok(message) => return Some(message),
+            Err(error) => error,
+        };
+
+        let path = match error.path().to_string().as_str() {
+            "." => path(),
+            inner => format!("{}.{inner}", path()),
+        };
+        let violation = SchemaViolation {
+            message_id: message_id(raw),
+            path,
+            error: without_position(error.inner()),
+        };
+        self.violations.borrow_mut().push(violation);
+        None
+    }
+
+    /// Takes every violation seen so far: an error in strict mode, the skipped messages in lenient mode.
+    pub fn check(&self) -> Result<Vec<SchemaViolation>, SchemaError> {
+        let violations = self.violations.take();
+        if self.mode == SchemaMode::Strict && !violations.is_empty() {
+            return Err(SchemaError(violations));
+        }
+        Ok(violations)
+    }
+}
+
+#[derive(Deserialize)]
+struct MessageId {
+    id: Option<u64>,
+}
+
+/// The id of a raw message, without deserializing the rest of it.
+pub(crate) fn message_id(raw: &str) -> Option<u64> {
+    serde_json::from_str::<MessageId>(raw).ok().and_then(|message| message.id)
+}
+
+/// Line and column are relative to the message itself, the JSON path is more useful.
+pub(crate) fn without_position(error: &serde_json::Error) -> String {
+    let suffix = format!(" at line {} column {}", error.line(), error.column());
+    let error = error.to_string();
+    error.strip_suffix(&suffix).unwrap_or(&error).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TextEntityType;
+
+    const VALID: &str = r#"{"id": 1, "type": "message", "date": "2023-01-12T15:04:05", "text": "hi", "text_entities": [{"type": "plain", "text": "hi"}]}"#;
+    const BAD_ENTITY: &str = r#"{"id": 2, "type": "message", "date": "2023-01-12T15:04:05", "text": "hi", "text_entities": [{"type": 5, "text": "hi"}]}"#;
+    const BAD_ID: &str = r#"{"id": "three", "type": "message", "date": "2023-01-12T15:04:05", "text": "", "text_entities": []}"#;
+
+    fn read(schema: &Schema) -> Vec<Option<u64>> {
+        [VALID, BAD_ENTITY, BAD_ID].iter().enumerate().map(|(i, raw)| schema.message(raw, || format!("messages[{i}]")).map(|message| message.id)).collect()
+    }
+
+    #[test]
+    fn locates_violations() {
+        let schema = Schema::new(SchemaMode::Lenient);
+        assert_eq!(read(&schema), [Some(1), None, None]);
+
+        let violations = schema.check().unwrap();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].path, "messages[1].text_entities[0].type");
+        assert_eq!(violations[0].message_id, Some(2));
+        assert!(!violations[0].error.contains("line"), "{}", violations[0].error);
+        assert_eq!(violations[1].path, "messages[2].id");
+        assert_eq!(violations[1].message_id, None);
+        assert_eq!(violations[1].to_string(), format!("message at messages[2].id: {}", violations[1].error));
+    }
+
+    #[test]
+    fn strict_mode_fails() {
+        let schema = Schema::new(SchemaMode::Strict);
+        read(&schema);
+        let error = schema.check().unwrap_err();
+        assert_eq!(error.0.iter().map(|violation| violation.path.as_str()).collect::<Vec<_>>(), ["messages[1].text_entities[0].type", "messages[2].id"]);
+        assert!(error.to_string().starts_with("2 message(s) don't match the export schema\n  message 2 at messages[1].text_entities[0].type: "));
+        // The violations were taken.
+        assert!(schema.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn valid_messages_pass_in_both_modes() {
+        for mode in [SchemaMode::Strict, SchemaMode::Lenient] {
+            let schema = Schema::new(mode);
+            assert_eq!(schema.message(VALID, || "messages[0]".to_string()).map(|message| message.id), Some(1));
+            assert!(schema.check().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn unknown_entity_types_are_not_violations() {
+        let raw = r#"{"id": 4, "type": "message", "date": "2023-01-12T15:04:05", "text": "hi", "text_entities": [{"type": "blockquote_v2", "text": "hi"}]}"#;
+        let schema = Schema::new(SchemaMode::Strict);
+        let message = schema.message(raw, || "messages[0]".to_string()).unwrap();
+        assert_eq!(message.text_entities[0].text_type, TextEntityType::Unknown("blockquote_v2".to_string()));
+        assert!(schema.check().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,12 @@
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::model::{Message, MessageType};
+
+/// The messages whose text matches `pattern`, in the order of the export.
+pub fn search<'m, 'a>(messages: &'m [Message<'a>], pattern: &Regex) -> Vec<&'m Message<'a>> {
+    messages
+        .par_iter()
+        .filter(|message| message.message_type != MessageType::Service && pattern.is_match(&message.text()))
+        .collect()
+}
@@ -0,0 +1,244 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::member::Member;
+use crate::model::Action;
+
+/// What the service messages of a chat say about its history.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ServiceStatistics<'a> {
+    /// Joins and leaves of every member, by display name: that is all `invite_members` and `remove_members` carry.
+    #[serde(borrow)]
+    pub membership: BTreeMap<Cow<'a, str>, Vec<MembershipEvent<'a>>>,
+    /// Every new title of the chat, oldest first.
+    #[serde(borrow)]
+    pub title_changes: Vec<TitleChange<'a>>,
+    /// Every new or deleted photo of the chat, oldest first.
+    #[serde(borrow)]
+    pub photo_changes: Vec<PhotoChange<'a>>,
+    /// Oldest first.
+    #[serde(borrow)]
+    pub pinned_messages: Vec<PinnedMessage<'a>>,
+    /// By the member who started them.
+    #[serde(borrow)]
+    pub calls: HashMap<Member<'a>, CallStatistics>,
+}
+
+/// A member joining or leaving, told by the service message `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipEvent<'a> {
+    /// The service message.
+    pub id: u64,
+    /// When it happened, in the zone of the analysis.
+    pub date: NaiveDateTime,
+    /// How the member joined or left.
+    pub kind: MembershipKind,
+    /// Who invited or removed the member, unset when they joined or left on their own.
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub by: Option<Cow<'a, str>>,
+}
+
+/// How a member joined or left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MembershipKind {
+    /// Listed as a member when the group was created.
+    Created,
+    /// Added by someone else.
+    Invited,
+    /// Joined on their own, by link or from the chat's page.
+    Joined,
+    /// Removed by someone else.
+    Removed,
+    /// Left on their own.
+    Left,
+}
+
+/// The chat renamed by the service message `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TitleChange<'a> {
+    /// The service message.
+    pub id: u64,
+    /// When it happened, in the zone of the analysis.
+    pub date: NaiveDateTime,
+    /// Who renamed the chat.
+    #[serde(borrow)]
+    pub actor: Option<Cow<'a, str>>,
+    /// The new title.
+    #[serde(borrow)]
+    pub title: Cow<'a, str>,
+}
+
+/// The chat photo changed by the service message `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhotoChange<'a> {
+    /// The service message.
+    pub id: u64,
+    /// When it happened, in the zone of the analysis.
+    pub date: NaiveDateTime,
+    /// Who changed the photo.
+    #[serde(borrow)]
+    pub actor: Option<Cow<'a, str>>,
+    /// The new photo, unset when it was deleted.
+    #[serde(borrow)]
+    pub photo: Option<Cow<'a, str>>,
+}
+
+/// A message pinned by the service message `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedMessage<'a> {
+    /// The service message.
+    pub id: u64,
+    /// When it happened, in the zone of the analysis.
+    pub date: NaiveDateTime,
+    /// Who pinned it.
+    #[serde(borrow)]
+    pub actor: Option<Cow<'a, str>>,
+    /// The pinned message.
+    pub message_id: Option<u64>,
+}
+
+/// Calls a member started, one-to-one (`phone_call`) or in the group (`group_call`).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CallStatistics {
+    /// One-to-one calls.
+    pub calls: usize,
+    /// Group calls.
+    pub group_calls: usize,
+    /// Calls that ended with the `missed` discard reason.
+    pub missed: usize,
+    /// Of every call together.
+    pub duration_seconds: u64,
+}
+
+impl<'a> Analyzer<'a> for ServiceStatistics<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        let message = observation.message;
+        let Some(action) = &message.action else {
+            return;
+        };
+        let (id, date) = (message.id, observation.date);
+        let actor = message.actor.as_deref().map(Cow::Borrowed);
+        let members = || message.members.iter().flatten().map(|member| Cow::Borrowed(member.as_str()));
+
+        match action {
+            Action::CreateGroup => {
+                for member in members() {
+                    self.membership_event(member, MembershipEvent { id, date, kind: MembershipKind::Created, by: None });
+                }
+            }
+            Action::InviteMembers => {
+                for member in members() {
+                    let event = MembershipEvent { id, date, kind: MembershipKind::Invited, by: actor.clone() };
+                    self.membership_event(member, event);
+                }
+            }
+            Action::RemoveMembers => {
+                for member in members() {
+                    let kind = if actor.as_ref() == Some(&member) { MembershipKind::Left } else { MembershipKind::Removed };
+                    let by = actor.clone().filter(|_| kind == MembershipKind::Removed);
+                    self.membership_event(member, MembershipEvent { id, date, kind, by });
+                }
+            }
+            Action::JoinGroupByLink | Action::JoinGroupByRequest => {
+                if let Some(actor) = actor {
+                    self.membership_event(actor, MembershipEvent { id, date, kind: MembershipKind::Joined, by: None });
+                }
+            }
+            Action::EditGroupTitle => self.title_changes.push(TitleChange {
+                id,
+                date,
+                actor,
+                title: Cow::Borrowed(message.title.as_deref().unwrap_or_default()),
+            }),
+            Action::EditGroupPhoto => self.photo_changes.push(PhotoChange { id, date, actor, photo: message.photo.as_deref().map(Cow::Borrowed) }),
+            Action::DeleteGroupPhoto => self.photo_changes.push(PhotoChange { id, date, actor, photo: None }),
+            Action::PinMessage => self.pinned_messages.push(PinnedMessage { id, date, actor, message_id: message.message_id }),
+            Action::PhoneCall | Action::GroupCall => {
+                let Some(member) = Member::actor(message) else {
+                    return;
+                };
+                let calls = self.calls.entry(member).or_default();
+                if *action == Action::PhoneCall {
+                    calls.calls += 1;
+                } else {
+                    calls.group_calls += 1;
+                }
+                calls.missed += usize::from(message.discard_reason.as_deref() == Some("missed"));
+                calls.duration_seconds += message.duration_seconds.unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (member, events) in other.membership {
+            let mergee = self.membership.entry(member).or_default();
+            mergee.extend(events);
+        }
+        self.title_changes.extend(other.title_changes);
+        self.photo_changes.extend(other.photo_changes);
+        self.pinned_messages.extend(other.pinned_messages);
+        for (member, calls) in other.calls {
+            let mergee = self.calls.entry(member).or_default();
+            mergee.calls += calls.calls;
+            mergee.group_calls += calls.group_calls;
+            mergee.missed += calls.missed;
+            mergee.duration_seconds += calls.duration_seconds;
+        }
+    }
+
+    /// Puts every list back in order once, rather than on every merge.
+    fn finish(&mut self) {
+        for events in self.membership.values_mut() {
+            events.sort_by_key(|event| (event.date, event.id));
+        }
+        self.title_changes.sort_by_key(|change| (change.date, change.id));
+        self.photo_changes.sort_by_key(|change| (change.date, change.id));
+        self.pinned_messages.sort_by_key(|pin| (pin.date, pin.id));
+    }
+}
+
+impl<'a> ServiceStatistics<'a> {
+    fn membership_event(&mut self, member: Cow<'a, str>, event: MembershipEvent<'a>) {
+        self.membership.entry(member).or_default().push(event);
+    }
+
+    /// Copies the names and titles borrowed from the messages.
+    pub fn into_owned(self) -> ServiceStatistics<'static> {
+        let owned = |value: Cow<'a, str>| Cow::Owned(value.into_owned());
+        ServiceStatistics {
+            membership: self
+                .membership
+                .into_iter()
+                .map(|(member, events)| {
+                    let events = events
+                        .into_iter()
+                        .map(|event| MembershipEvent { id: event.id, date: event.date, kind: event.kind, by: event.by.map(owned) })
+                        .collect();
+                    (owned(member), events)
+                })
+                .collect(),
+            title_changes: self
+                .title_changes
+                .into_iter()
+                .map(|change| TitleChange { id: change.id, date: change.date, actor: change.actor.map(owned), title: owned(change.title) })
+                .collect(),
+            photo_changes: self
+                .photo_changes
+                .into_iter()
+                .map(|change| PhotoChange { id: change.id, date: change.date, actor: change.actor.map(owned), photo: change.photo.map(owned) })
+                .collect(),
+            pinned_messages: self
+                .pinned_messages
+                .into_iter()
+                .map(|pin| PinnedMessage { id: pin.id, date: pin.date, actor: pin.actor.map(owned), message_id: pin.message_id })
+                .collect(),
+            calls: self.calls.into_iter().map(|(member, calls)| (member.into_owned(), calls)).collect(),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::morphology::Language;
+use crate::normalize::Normalization;
+
+/// Words too common to tell anything about a chat, left out of the token counts.
+#[derive(Debug, Default)]
+pub struct Stopwords(HashSet<String>);
+
+impl Stopwords {
+    /// Merges the bundled lists of `languages` with `files` of one word per line, `#` starts a comment.
+    /// Words are normalized like tokens are, otherwise they would never match.
+    pub fn load(languages: &[Language], files: &[PathBuf], normalization: &Normalization) -> Result<Self> {
+        let mut lists: Vec<Cow<'static, str>> = languages.iter().map(|&language| Cow::Borrowed(bundled(language))).collect();
+        for file in files {
+            lists.push(Cow::Owned(fs::read_to_string(file).map_err(Error::io(file))?));
+        }
+        let words = lists
+            .iter()
+            .flat_map(|list| list.lines())
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|word| !word.is_empty())
+            .map(|word| normalization.apply(Cow::Borrowed(word)).into_owned())
+            .collect();
+        Ok(Stopwords(words))
+    }
+
+    /// Whether `token`, normalized like the stopwords were, is one of them.
+    pub fn contains(&self, token: &str) -> bool {
+        self.0.contains(token)
+    }
+}
+
+fn bundled(language: Language) -> &'static str {
+    match language {
+        Language::En => include_str!("stopwords/en.txt"),
+        Language::Ru => include_str!("stopwords/ru.txt"),
+        Language::Uk => include_str!("stopwords/uk.txt"),
+    }
+}
@@ -0,0 +1,591 @@
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::account::{Account, AccountStatistics, ChatFilter, ChatReport, Contacts, Export, ExportStatistics, PersonalInformation};
+use crate::analyzer::{self, Detached};
+use crate::error::{Error, Result};
+use crate::model::{Chat, ChatType, Id, Message};
+use crate::schema::{self, Schema};
+use crate::Options;
+
+/// Gathers statistics from an export without ever holding more than `batch_size` messages in memory.
+///
+/// Both single-chat and account exports are accepted; `filter` only applies to the latter.
+pub fn gather<A: Detached + Clone, R: io::Read>(reader: R, batch_size: usize, filter: &ChatFilter, schema: &Schema, options: &Options) -> Result<ExportStatistics<A>> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let walked = walk(&mut deserializer, &Batches { batch_size, options, analyzer: PhantomData }, filter, schema)?;
+    deserializer.end().map_err(|error| Error::json(error, None, None))?;
+
+    let report = |chat: Entry<Batch<A>>, left| ChatReport { name: chat.name.into_owned(), chat_type: chat.chat_type, id: chat.id, left, statistics: chat.messages.stat };
+    Ok(match walked {
+        Walked::Chat(chat) => ExportStatistics::Chat(chat.messages.stat),
+        Walked::Account { personal_information, chats, left_chats, .. } => {
+            let chats = chats.into_iter().map(|chat| report(chat, false));
+            let left_chats = left_chats.into_iter().map(|chat| report(chat, true));
+            ExportStatistics::Account(AccountStatistics::from_reports(personal_information, chats.chain(left_chats).collect()))
+        }
+    })
+}
+
+/// Deserializes a whole export, borrowing every string it can from `content`.
+pub fn collect<'de>(content: &'de str, filter: &ChatFilter, schema: &Schema) -> Result<Export<'de>> {
+    let mut deserializer = serde_json::Deserializer::from_str(content);
+    let walked = walk(&mut deserializer, &Collect, filter, schema)?;
+    deserializer.end().map_err(|error| Error::json(error, None, None))?;
+
+    let chat = |chat: Entry<'de, Vec<Message<'de>>>| Chat { name: chat.name, chat_type: chat.chat_type, id: chat.id, messages: chat.messages };
+    Ok(match walked {
+        Walked::Chat(entry) => Export::Chat(chat(entry)),
+        Walked::Account { personal_information, contacts, chats, left_chats } => Export::Account(Account {
+            personal_information,
+            contacts,
+            chats: chats.into_iter().map(chat).collect(),
+            left_chats: left_chats.into_iter().map(chat).collect(),
+        }),
+    })
+}
+
+/// Decides what happens to the messages of each chat as they are read.
+trait Gatherer<'de> {
+    type Messages: Default;
+    /// What a message is read as, it is only checked against the schema by the gatherer.
+    type Raw: Deserialize<'de>;
+
+    /// `raw` is element `index` of the `messages` array at `path`.
+    fn push(&self, messages: &mut Self::Messages, raw: Self::Raw, schema: &Schema, path: &str, index: usize);
+
+    /// Called once the whole `messages` array has been read.
+    fn finish(&self, _messages: &mut Self::Messages, _schema: &Schema) {}
+
+    /// The id of the last message pushed, to tell where the export broke.
+    fn last_id(&self, messages: &Self::Messages) -> Option<u64>;
+}
+
+/// Keeps every message.
+struct Collect;
+
+impl<'de> Gatherer<'de> for Collect {
+    type Messages = Vec<Message<'de>>;
+    type Raw = &'de RawValue;
+
+    fn push(&self, messages: &mut Self::Messages, raw: &'de RawValue, schema: &Schema, path: &str, index: usize) {
+        if let Some(message) = schema.message(raw.get(), || format!("{path}[{index}]")) {
+            messages.push(message);
+        }
+    }
+
+    fn last_id(&self, messages: &Self::Messages) -> Option<u64> {
+        messages.last().map(|message| message.id)
+    }
+}
+
+/// Gathers statistics `batch_size` messages at a time.
+struct Batches<'o, A> {
+    batch_size: usize,
+    options: &'o Options,
+    analyzer: PhantomData<A>,
+}
+
+/// Messages are kept as raw JSON until the batch is full, so the deserialized ones can borrow from it.
+#[derive(Default)]
+struct Batch<A> {
+    raw: Vec<Box<RawValue>>,
+    /// Where `raw[0]` is in the export.
+    path: String,
+    first: usize,
+    /// Of the last message flushed.
+    last_id: Option<u64>,
+    stat: A,
+}
+
+impl<A: Detached> Batch<A> {
+    fn flush(&mut self, schema: &Schema, options: &Options) {
+        let (path, first) = (&self.path, self.first);
+        let mut messages: Vec<Message> = Vec::with_capacity(self.raw.len());
+        messages.extend(self.raw.iter().enumerate().filter_map(|(i, raw)| schema.message(raw.get(), || format!("{path}[{}]", first + i))));
+        self.stat.merge(A::detach(analyzer::fold(&messages, options)));
+        self.last_id = messages.last().map(|message| message.id).or(self.last_id);
+        drop(messages);
+        self.first += self.raw.len();
+        self.raw.clear();
+    }
+}
+
+impl<'de, A: Detached> Gatherer<'de> for Batches<'_, A> {
+    type Messages = Batch<A>;
+    type Raw = Box<RawValue>;
+
+    fn push(&self, batch: &mut Batch<A>, raw: Box<RawValue>, schema: &Schema, path: &str, index: usize) {
+        if batch.raw.is_empty() && index == 0 {
+            batch.path = path.to_string();
+        }
+        batch.raw.push(raw);
+        if batch.raw.len() >= self.batch_size {
+            batch.flush(schema, self.options);
+        }
+    }
+
+    fn finish(&self, batch: &mut Batch<A>, schema: &Schema) {
+        if !batch.raw.is_empty() {
+            batch.flush(schema, self.options);
+        }
+        // Replies to earlier batches are only resolved now.
+        batch.stat.finish();
+    }
+
+    fn last_id(&self, batch: &Batch<A>) -> Option<u64> {
+        batch.raw.last().and_then(|raw| schema::message_id(raw.get())).or(batch.last_id)
+    }
+}
+
+struct Entry<'de, M> {
+    name: Cow<'de, str>,
+    chat_type: ChatType,
+    id: Id,
+    messages: M,
+}
+
+enum Walked<'de, M> {
+    Chat(Entry<'de, M>),
+    Account {
+        personal_information: Option<PersonalInformation>,
+        contacts: Option<Contacts>,
+        chats: Vec<Entry<'de, M>>,
+        left_chats: Vec<Entry<'de, M>>,
+    },
+}
+
+fn walk<'de, R: serde_json::de::Read<'de>, G: Gatherer<'de>>(
+    deserializer: &mut serde_json::Deserializer<R>,
+    gatherer: &G,
+    filter: &ChatFilter,
+    schema: &Schema,
+) -> Result<Walked<'de, G::Messages>> {
+    let failure = RefCell::new(None);
+    let walk = Walk { gatherer, filter, schema, failure: &failure };
+    let object = ObjectSeed { walk, path: String::new(), listed: false }.deserialize(deserializer).map_err(|error| {
+        let (location, message_id) = failure.take().unzip();
+        Error::json(error, location, message_id.flatten())
+    })?;
+
+    if object.chats.is_some() || object.left_chats.is_some() {
+        return Ok(Walked::Account {
+            personal_information: object.personal_information,
+            contacts: object.contacts,
+            chats: object.chats.unwrap_or_default(),
+            left_chats: object.left_chats.unwrap_or_default(),
+        });
+    }
+    object.into_entry().map(Walked::Chat).map_err(|error| Error::json(error, None, None))
+}
+
+/// State shared by every level of the walk.
+struct Walk<'w, G> {
+    gatherer: &'w G,
+    filter: &'w ChatFilter,
+    schema: &'w Schema,
+    /// The message being read when the export turned out to be invalid, and the id of the one before.
+    failure: &'w RefCell<Option<(String, Option<u64>)>>,
+}
+
+impl<G> Clone for Walk<'_, G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<G> Copy for Walk<'_, G> {}
+
+/// The keys of either a single chat or a whole account, whichever the object turns out to be.
+struct Object<'de, M> {
+    name: Option<Cow<'de, str>>,
+    chat_type: Option<ChatType>,
+    id: Option<Id>,
+    messages: Option<M>,
+    personal_information: Option<PersonalInformation>,
+    contacts: Option<Contacts>,
+    chats: Option<Vec<Entry<'de, M>>>,
+    left_chats: Option<Vec<Entry<'de, M>>>,
+}
+
+impl<'de, M: Default> Object<'de, M> {
+    fn into_entry<E: de::Error>(self) -> Result<Entry<'de, M>, E> {
+        Ok(Entry {
+            // Chats of deleted accounts have no name at all.
+            name: self.name.unwrap_or_default(),
+            chat_type: self.chat_type.ok_or_else(|| E::missing_field("type"))?,
+            id: self.id.ok_or_else(|| E::missing_field("id"))?,
+            messages: self.messages.unwrap_or_default(),
+        })
+    }
+}
+
+/// Walks the top-level object, or a chat of an account's `chats.list`/`left_chats.list` if `listed`.
+///
+/// Telegram writes `name`, `type` and `id` before `messages`, which lets chats rejected by the
+/// filter skip their messages entirely instead of gathering them only to throw the result away.
+struct ObjectSeed<'w, G> {
+    walk: Walk<'w, G>,
+    path: String,
+    listed: bool,
+}
+
+impl<'de, G: Gatherer<'de>> DeserializeSeed<'de> for ObjectSeed<'_, G> {
+    type Value = Object<'de, G::Messages>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, G: Gatherer<'de>> Visitor<'de> for ObjectSeed<'_, G> {
+    type Value = Object<'de, G::Messages>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.listed {
+            formatter.write_str("a chat object")
+        } else {
+            formatter.write_str("a chat or an account export")
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut object = Object {
+            name: None,
+            chat_type: None,
+            id: None,
+            messages: None,
+            personal_information: None,
+            contacts: None,
+            chats: None,
+            left_chats: None,
+        };
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => object.name = map.next_value::<Option<Borrowed>>()?.map(|name| name.0),
+                "type" => object.chat_type = Some(map.next_value()?),
+                "id" => object.id = Some(map.next_value()?),
+                "messages" => {
+                    let rejected = match (&object.name, &object.chat_type, &object.id) {
+                        (Some(name), Some(chat_type), Some(id)) => self.listed && !self.walk.filter.matches(name, chat_type, id),
+                        _ => false,
+                    };
+                    if rejected {
+                        map.next_value::<IgnoredAny>()?;
+                    } else {
+                        let path = join(&self.path, "messages");
+                        object.messages = Some(map.next_value_seed(MessagesSeed { walk: self.walk, path })?);
+                    }
+                }
+                "personal_information" if !self.listed => object.personal_information = Some(map.next_value()?),
+                "contacts" if !self.listed => object.contacts = Some(map.next_value()?),
+                "chats" | "left_chats" if !self.listed => {
+                    let chats = map.next_value_seed(ChatListSeed { walk: self.walk, path: key.clone() })?;
+                    if key == "chats" {
+                        object.chats = Some(chats);
+                    } else {
+                        object.left_chats = Some(chats);
+                    }
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(object)
+    }
+}
+
+/// Walks `{ "about": ..., "list": [chat, ...] }`, keeping the chats selected by the filter.
+struct ChatListSeed<'w, G> {
+    walk: Walk<'w, G>,
+    path: String,
+}
+
+impl<'de, G: Gatherer<'de>> DeserializeSeed<'de> for ChatListSeed<'_, G> {
+    type Value = Vec<Entry<'de, G::Messages>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, G: Gatherer<'de>> Visitor<'de> for ChatListSeed<'_, G> {
+    type Value = Vec<Entry<'de, G::Messages>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of chats")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut chats = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "list" {
+                let path = join(&self.path, "list");
+                chats = map.next_value_seed(ChatsSeed { walk: self.walk, path })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(chats)
+    }
+}
+
+struct ChatsSeed<'w, G> {
+    walk: Walk<'w, G>,
+    path: String,
+}
+
+impl<'de, G: Gatherer<'de>> DeserializeSeed<'de> for ChatsSeed<'_, G> {
+    type Value = Vec<Entry<'de, G::Messages>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, G: Gatherer<'de>> Visitor<'de> for ChatsSeed<'_, G> {
+    type Value = Vec<Entry<'de, G::Messages>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of chats")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut chats = Vec::new();
+        let mut index = 0;
+        while let Some(object) = seq.next_element_seed(ObjectSeed { walk: self.walk, path: format!("{}[{index}]", self.path), listed: true })? {
+            let chat = object.into_entry()?;
+            if self.walk.filter.matches(&chat.name, &chat.chat_type, &chat.id) {
+                chats.push(chat);
+            }
+            index += 1;
+        }
+        Ok(chats)
+    }
+}
+
+/// Walks a `messages` array, checking every message against the schema before handing it to the gatherer.
+struct MessagesSeed<'w, G> {
+    walk: Walk<'w, G>,
+    path: String,
+}
+
+impl<'de, G: Gatherer<'de>> DeserializeSeed<'de> for MessagesSeed<'_, G> {
+    type Value = G::Messages;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, G: Gatherer<'de>> Visitor<'de> for MessagesSeed<'_, G> {
+    type Value = G::Messages;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of messages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut messages = G::Messages::default();
+        let mut index = 0;
+        loop {
+            let raw = match seq.next_element::<G::Raw>() {
+                Ok(Some(raw)) => raw,
+                Ok(None) => break,
+                Err(error) => {
+                    let last_id = self.walk.gatherer.last_id(&messages);
+                    self.walk.failure.replace(Some((format!("{}[{index}]", self.path), last_id)));
+                    return Err(error);
+                }
+            };
+            self.walk.gatherer.push(&mut messages, raw, self.walk.schema, &self.path, index);
+            index += 1;
+        }
+        self.walk.gatherer.finish(&mut messages, self.walk.schema);
+        Ok(messages)
+    }
+}
+
+/// `Cow<str>` alone always copies, `#[serde(borrow)]` makes it borrow strings without escapes.
+#[derive(Deserialize)]
+struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::ExportStatistics;
+    use crate::member::Member;
+    use crate::model::MemberId;
+    use crate::schema::SchemaMode;
+    use crate::ChatStatistics;
+
+    /// A message of `user`, with `extra` fields such as a reply appended.
+    fn message(id: u64, user: u64, text: &str, extra: &str) -> String {
+        let date = format!("2023-01-{:02}T{:02}:04:05", 1 + id % 28, id % 24);
+        format!(r#"{{"id": {id}, "type": "message", "date": "{date}", "from": "User {user}", "from_id": "user{user}", "text": "{text}", "text_entities": [{{"type": "plain", "text": "{text}"}}]{extra}}}"#)
+    }
+
+    fn chat(name: &str, chat_type: &str, id: u64, messages: &[String]) -> String {
+        format!(r#"{{"name": "{name}", "type": "{chat_type}", "id": {id}, "messages": [{}]}}"#, messages.join(", "))
+    }
+
+    fn account(chats: &[String]) -> String {
+        format!(r#"{{"about": "Account", "chats": {{"about": "Chats", "list": [{}]}}}}"#, chats.join(", "))
+    }
+
+    /// Replies, edits, emojis, escapes and a service message, spread over three members.
+    fn conversation() -> Vec<String> {
+        vec![
+            message(1, 1, "the quick brown fox", ""),
+            message(2, 2, "jumps over the lazy dog", r#", "reply_to_message_id": 1"#),
+            message(3, 3, "the lazy dog sleeps 🙂", r#", "reply_to_message_id": 1"#),
+            r#"{"id": 4, "type": "service", "date": "2023-01-05T04:04:05", "actor": "User 1", "actor_id": "user1", "action": "invite_members", "members": ["User 4"], "text": "", "text_entities": []}"#.to_string(),
+            message(5, 4, "hello \\\"everyone\\\"", ""),
+            message(6, 1, "the quick brown fox again", r#", "reply_to_message_id": 5, "edited": "2023-01-07T06:05:00""#),
+            message(7, 2, "quick quick 🙂🙂", r#", "reply_to_message_id": 6"#),
+            message(8, 3, "над лінивим псом", r#", "reply_to_message_id": 2"#),
+            message(9, 1, "Über straße", ""),
+            message(10, 4, "fox", r#", "reply_to_message_id": 3"#),
+        ]
+    }
+
+    fn options() -> Options {
+        Options { ngrams: vec![2, 3], ..Default::default() }
+    }
+
+    fn json(mut stat: ExportStatistics<ChatStatistics>) -> serde_json::Value {
+        stat.rank(10);
+        stat.summarize();
+        serde_json::to_value(stat).unwrap()
+    }
+
+    fn streamed(export: &str, batch_size: usize, filter: &ChatFilter) -> serde_json::Value {
+        let schema = Schema::new(SchemaMode::Strict);
+        let stat = gather::<ChatStatistics, _>(export.as_bytes(), batch_size, filter, &schema, &options()).unwrap();
+        schema.check().unwrap();
+        json(stat)
+    }
+
+    fn collected(export: &str, filter: &ChatFilter) -> serde_json::Value {
+        let schema = Schema::new(SchemaMode::Strict);
+        let export = collect(export, filter, &schema).unwrap();
+        let stat = match &export {
+            Export::Chat(chat) => ExportStatistics::Chat(ChatStatistics::gather(chat, &options())),
+            Export::Account(account) => ExportStatistics::Account(AccountStatistics::gather(account, &options())),
+        };
+        schema.check().unwrap();
+        json(stat)
+    }
+
+    fn chat_types(export: &Export) -> Vec<(String, ChatType)> {
+        match export {
+            Export::Account(account) => account.chats.iter().map(|chat| (chat.name.to_string(), chat.chat_type.clone())).collect(),
+            Export::Chat(_) => panic!("not an account export"),
+        }
+    }
+
+    #[test]
+    fn batches_gather_what_collecting_does() {
+        let export = chat("Team", "private_supergroup", 1, &conversation());
+        let expected = collected(&export, &ChatFilter::default());
+        for batch_size in [1, 2, 3, 4, 10, 1000] {
+            assert_eq!(streamed(&export, batch_size, &ChatFilter::default()), expected, "batch size {batch_size}");
+        }
+    }
+
+    #[test]
+    fn batches_gather_what_collecting_does_for_accounts() {
+        let export = account(&[
+            chat("Team", "private_supergroup", 1, &conversation()),
+            chat("User 2", "personal_chat", 2, &[message(1, 1, "hi", ""), message(2, 2, "hi there", r#", "reply_to_message_id": 1"#)]),
+        ]);
+        let expected = collected(&export, &ChatFilter::default());
+        for batch_size in [1, 2, 7] {
+            assert_eq!(streamed(&export, batch_size, &ChatFilter::default()), expected, "batch size {batch_size}");
+        }
+    }
+
+    #[test]
+    fn replies_are_credited_across_batches() {
+        let export = chat("Team", "private_supergroup", 1, &conversation());
+        let schema = Schema::new(SchemaMode::Strict);
+        let ExportStatistics::Chat(stat) = gather::<ChatStatistics, _>(export.as_bytes(), 1, &ChatFilter::default(), &schema, &options()).unwrap() else {
+            panic!("not a single chat export");
+        };
+        let received = |user| stat.activity.members[&Member::Id(MemberId::User(user))].replies_received;
+        assert_eq!([received(1), received(2), received(3), received(4)], [3, 1, 1, 1]);
+    }
+
+    #[test]
+    fn skips_the_messages_of_filtered_out_chats() {
+        // Neither of these is a message, but the chat is never read past its `type` and `id`.
+        let broken = [r#"{"id": "two", "type": "message"}"#.to_string(), r#"{"date": 5}"#.to_string()];
+        let export = account(&[chat("Broken", "private_group", 2, &broken), chat("Team", "private_supergroup", 1, &conversation())]);
+        let team = ChatFilter { names: vec!["Team".to_string()], ..Default::default() };
+
+        let expected = collected(&account(&[chat("Team", "private_supergroup", 1, &conversation())]), &ChatFilter::default());
+        assert_eq!(collected(&export, &team), expected);
+        for batch_size in [1, 3] {
+            assert_eq!(streamed(&export, batch_size, &team), expected, "batch size {batch_size}");
+        }
+    }
+
+    #[test]
+    fn reports_where_a_truncated_export_ends() {
+        let messages = conversation();
+        let full = account(&[chat("Team", "private_supergroup", 1, &messages)]);
+        // Cut in the middle of the sixth message, after the fifth one.
+        let cut = full.find(&messages[5]).unwrap() + 30;
+        for batch_size in [1, 2, 3, 100] {
+            let error = gather::<ChatStatistics, _>(&full.as_bytes()[..cut], batch_size, &ChatFilter::default(), &Schema::default(), &options()).unwrap_err();
+            match error {
+                Error::Json { location, message_id, .. } => {
+                    assert_eq!(location.as_deref(), Some("chats.list[0].messages[5]"), "batch size {batch_size}");
+                    assert_eq!(message_id, Some(5), "batch size {batch_size}");
+                }
+                error => panic!("{error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn selects_and_skips_chats_of_unknown_types() {
+        let export = account(&[
+            chat("Team", "private_supergroup", 1, &[message(1, 1, "hi", "")]),
+            chat("Replies", "replies", 2, &[message(2, 2, "re", "")]),
+            chat("Future", "something_new", 3, &[message(3, 3, "new", "")]),
+        ]);
+        let schema = Schema::new(SchemaMode::Strict);
+
+        let every = collect(&export, &ChatFilter::default(), &schema).unwrap();
+        assert_eq!(chat_types(&every), [
+            ("Team".to_string(), ChatType::PrivateSupergroup),
+            ("Replies".to_string(), ChatType::Replies),
+            ("Future".to_string(), ChatType::Unknown("something_new".to_string())),
+        ]);
+
+        let future = ChatFilter { names: vec!["Future".to_string()], ..Default::default() };
+        let selected = collect(&export, &future, &schema).unwrap();
+        assert_eq!(chat_types(&selected), [("Future".to_string(), ChatType::Unknown("something_new".to_string()))]);
+
+        let team = ChatFilter { types: vec![ChatType::PrivateSupergroup], ..Default::default() };
+        assert_eq!(chat_types(&collect(&export, &team, &schema).unwrap()), [("Team".to_string(), ChatType::PrivateSupergroup)]);
+        assert!(schema.check().unwrap().is_empty());
+    }
+}
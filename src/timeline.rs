@@ -0,0 +1,211 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::member::Member;
+use crate::model::MessageType;
+
+/// When messages were sent, overall and by every member; service messages aside.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Timeline<'a> {
+    /// Of every message.
+    pub overall: Series,
+    /// Of the messages of every member.
+    #[serde(borrow)]
+    pub members: HashMap<Member<'a>, Series>,
+}
+
+/// When a set of messages were sent.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Series {
+    /// Counts of the days with any message.
+    pub days: BTreeMap<NaiveDate, Counts>,
+    /// Messages by weekday, from Monday, and hour of the day.
+    pub heatmap: [[usize; 24]; 7],
+}
+
+/// What was sent in a day or a longer period.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counts {
+    /// Messages sent.
+    pub messages: usize,
+    /// Tokens in them, stopwords aside.
+    pub tokens: usize,
+}
+
+/// The counts of a period, for plotting.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Bucket {
+    /// The first day of the period.
+    pub start: NaiveDate,
+    /// Of every day of the period.
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+impl<'a> Analyzer<'a> for Timeline<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        let message = observation.message;
+        if message.message_type == MessageType::Service {
+            return;
+        }
+        let tokens = observation.entities.iter().map(|entity| entity.tokens.len()).sum();
+        self.overall.observe(observation.date, tokens);
+        if let Some(from) = &observation.from {
+            self.members.entry(from.clone()).or_default().observe(observation.date, tokens);
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.overall.merge(other.overall);
+        for (member, series) in other.members {
+            self.members.entry(member).or_default().merge(series);
+        }
+    }
+}
+
+impl Timeline<'_> {
+    /// Copies the members borrowed from the messages.
+    pub fn into_owned(self) -> Timeline<'static> {
+        Timeline {
+            overall: self.overall,
+            members: self.members.into_iter().map(|(member, series)| (member.into_owned(), series)).collect(),
+        }
+    }
+}
+
+impl Series {
+    fn observe(&mut self, date: NaiveDateTime, tokens: usize) {
+        let counts = self.days.entry(date.date()).or_default();
+        counts.messages += 1;
+        counts.tokens += tokens;
+        self.heatmap[date.weekday().num_days_from_monday() as usize][date.hour() as usize] += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (day, counts) in other.days {
+            let mergee = self.days.entry(day).or_default();
+            mergee.messages += counts.messages;
+            mergee.tokens += counts.tokens;
+        }
+        for (weekday, hours) in other.heatmap.iter().enumerate() {
+            for (hour, messages) in hours.iter().enumerate() {
+                self.heatmap[weekday][hour] += messages;
+            }
+        }
+    }
+
+    /// Sums the days up by `period`, from the first period with a message to the last, quiet ones included.
+    pub fn by(&self, period: Period) -> Vec<Bucket> {
+        let (Some(first), Some(last)) = (self.days.keys().next(), self.days.keys().next_back()) else {
+            return Vec::new();
+        };
+        let mut buckets = Vec::new();
+        let mut start = period.start(*first);
+        while start <= *last {
+            let end = period.next(start);
+            let mut counts = Counts::default();
+            for day_counts in self.days.range(start..end).map(|(_, counts)| counts) {
+                counts.messages += day_counts.messages;
+                counts.tokens += day_counts.tokens;
+            }
+            buckets.push(Bucket { start, counts });
+            start = end;
+        }
+        buckets
+    }
+}
+
+/// How long a `Bucket` is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    /// Calendar days
+    #[default]
+    Day,
+    /// Weeks start on Monday
+    Week,
+    /// Calendar months
+    Month,
+    /// Calendar years
+    Year,
+}
+
+impl Period {
+    /// The first day of the period `date` falls in.
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        let offset = match self {
+            Period::Day => 0,
+            Period::Week => date.weekday().num_days_from_monday(),
+            Period::Month => date.day0(),
+            Period::Year => date.ordinal0(),
+        };
+        date - Days::new(offset.into())
+    }
+
+    /// The first day of the period after the one starting at `start`.
+    pub fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => start + Days::new(1),
+            Period::Week => start + Days::new(7),
+            Period::Month => start + Months::new(1),
+            Period::Year => start + Months::new(12),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn periods_start() {
+        // A Thursday.
+        let date = day(2024, 2, 29);
+        assert_eq!(Period::Day.start(date), date);
+        assert_eq!(Period::Week.start(date), day(2024, 2, 26));
+        assert_eq!(Period::Month.start(date), day(2024, 2, 1));
+        assert_eq!(Period::Year.start(date), day(2024, 1, 1));
+    }
+
+    #[test]
+    fn periods_start_on_their_first_day() {
+        assert_eq!(Period::Week.start(day(2024, 2, 26)), day(2024, 2, 26));
+        assert_eq!(Period::Week.start(day(2024, 3, 3)), day(2024, 2, 26));
+        assert_eq!(Period::Week.start(day(2024, 1, 3)), day(2024, 1, 1));
+        assert_eq!(Period::Week.start(day(2023, 1, 1)), day(2022, 12, 26));
+        assert_eq!(Period::Month.start(day(2024, 3, 1)), day(2024, 3, 1));
+        assert_eq!(Period::Year.start(day(2024, 12, 31)), day(2024, 1, 1));
+    }
+
+    #[test]
+    fn periods_follow_each_other() {
+        assert_eq!(Period::Day.next(day(2024, 2, 28)), day(2024, 2, 29));
+        assert_eq!(Period::Day.next(day(2023, 12, 31)), day(2024, 1, 1));
+        assert_eq!(Period::Week.next(day(2024, 2, 26)), day(2024, 3, 4));
+        assert_eq!(Period::Week.next(day(2022, 12, 26)), day(2023, 1, 2));
+        assert_eq!(Period::Month.next(day(2024, 1, 1)), day(2024, 2, 1));
+        assert_eq!(Period::Month.next(day(2024, 12, 1)), day(2025, 1, 1));
+        assert_eq!(Period::Year.next(day(2024, 1, 1)), day(2025, 1, 1));
+    }
+
+    #[test]
+    fn buckets_include_quiet_periods() {
+        let mut series = Series::default();
+        series.observe(day(2024, 1, 31).and_hms_opt(23, 59, 0).unwrap(), 2);
+        series.observe(day(2024, 1, 31).and_hms_opt(8, 0, 0).unwrap(), 1);
+        series.observe(day(2024, 3, 1).and_hms_opt(0, 0, 0).unwrap(), 5);
+
+        let buckets: Vec<_> = series.by(Period::Month).into_iter().map(|bucket| (bucket.start, bucket.counts.messages, bucket.counts.tokens)).collect();
+        assert_eq!(buckets, [(day(2024, 1, 1), 2, 3), (day(2024, 2, 1), 0, 0), (day(2024, 3, 1), 1, 5)]);
+        assert_eq!(series.by(Period::Week).len(), 5);
+        assert!(Series::default().by(Period::Day).is_empty());
+    }
+}
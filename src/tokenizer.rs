@@ -0,0 +1,71 @@
+use regex::Regex;
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::fmt::Debug;
+
+use crate::error::{Error, Result};
+
+/// Splits the text of an entity into the tokens that get counted.
+pub trait Tokenizer: Debug + Send + Sync {
+    /// The tokens of `text` in order, none of them empty.
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<&'t str>;
+}
+
+/// Which `Tokenizer` to build.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenizerKind {
+    /// Unicode (UAX #29) word boundaries, dropping punctuation and emojis
+    #[default]
+    Words,
+    /// Split on spaces and a few ASCII punctuation characters, as before
+    Separators,
+    /// Every match of `--token-pattern` is a token
+    Regex,
+}
+
+/// Words as delimited by UAX #29, keeping only those with at least one alphanumeric character.
+#[derive(Debug, Clone, Copy)]
+pub struct Words;
+
+impl Tokenizer for Words {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        text.unicode_words().collect()
+    }
+}
+
+/// Splits on any of the given characters.
+#[derive(Debug, Clone, Copy)]
+pub struct Separators(pub &'static [char]);
+
+/// The characters the original tokenizer split on.
+pub const SEPARATORS: [char; 12] = [' ', ',', '.','(', ')', '-', '!', '?', '\'', '\"', '\n', '\t'];
+
+impl Tokenizer for Separators {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        text.split(self.0).filter(|s| !s.is_empty()).collect()
+    }
+}
+
+/// Every match of the regex is a token.
+#[derive(Debug, Clone)]
+pub struct Pattern(pub Regex);
+
+impl Tokenizer for Pattern {
+    fn tokenize<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        self.0.find_iter(text).map(|token| token.as_str()).filter(|s| !s.is_empty()).collect()
+    }
+}
+
+impl TokenizerKind {
+    /// `pattern` is only used, and required, by `TokenizerKind::Regex`.
+    pub fn build(self, pattern: Option<Regex>) -> Result<Box<dyn Tokenizer>> {
+        Ok(match self {
+            TokenizerKind::Words => Box::new(Words),
+            TokenizerKind::Separators => Box::new(Separators(&SEPARATORS)),
+            TokenizerKind::Regex => Box::new(Pattern(pattern.ok_or_else(|| Error::Analysis("the regex tokenizer needs a pattern".to_string()))?)),
+        })
+    }
+}
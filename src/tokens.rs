@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::analyzer::{Analyzer, Observation};
+use crate::member::Member;
+use crate::{merge_maps_with, Token};
+
+/// Occurrences of every token overall and per member.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TokenStatistics<'a> {
+    /// Tokens counted, stopwords aside.
+    pub num_tokens: usize,
+    /// `num_tokens` plus the number of distinct stopwords left out.
+    #[serde(default)]
+    pub num_unfiltered_tokens: usize,
+    /// Occurrences of every token per member.
+    #[serde(borrow)]
+    pub members_tokens_map: HashMap<Member<'a>, HashMap<Token<'a>, usize>>,
+    /// Occurrences of every token.
+    #[serde(borrow)]
+    pub tokens_map: HashMap<Token<'a>, usize>,
+    /// Occurrences of the stopwords left out of `tokens_map`.
+    #[serde(borrow, default, skip_serializing_if = "HashMap::is_empty")]
+    pub stopwords_map: HashMap<Token<'a>, usize>,
+    /// The words counted under every lemma or stem, when those are enabled.
+    #[serde(borrow, default, skip_serializing_if = "HashMap::is_empty")]
+    pub variants: HashMap<Token<'a>, BTreeSet<Token<'a>>>,
+}
+
+impl<'a> Analyzer<'a> for TokenStatistics<'a> {
+    fn observe(&mut self, observation: &Observation<'a, '_>) {
+        for entity in &observation.entities {
+            for stopword in &entity.stopwords {
+                *self.stopwords_map.entry(stopword.clone()).or_insert(0) += 1;
+            }
+            for (canonical, surface) in entity.tokens.iter().zip(&entity.surfaces) {
+                self.variants.entry(canonical.clone()).or_default().insert(surface.clone());
+            }
+            for token in &entity.tokens {
+                *self.tokens_map.entry(token.clone()).or_insert(0) += 1;
+                let Some(from) = &observation.from else {
+                    continue;
+                };
+                if let Some(map) =  self.members_tokens_map.get_mut(from) {
+                    *map.entry(token.clone()).or_insert(0) += 1;
+                } else  {
+                    let member_occurences_map = HashMap::from([(token.clone(), 1)]);
+                    self.members_tokens_map.insert(from.clone(), member_occurences_map);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_maps_with(&mut self.tokens_map, other.tokens_map, |tokens_map, token, occurences| *tokens_map.entry(token).or_insert(0) += occurences);
+        for (member, map) in other.members_tokens_map {
+            if let Some(mergee) = self.members_tokens_map.get_mut(&member) {
+                merge_maps_with(mergee, map, |mergee, member, occurences| *mergee.entry(member).or_insert(0) += occurences);
+            } else {
+                self.members_tokens_map.insert(member, map);
+            }
+        }
+        merge_maps_with(&mut self.stopwords_map, other.stopwords_map, |stopwords_map, token, occurences| *stopwords_map.entry(token).or_insert(0) += occurences);
+        for (canonical, variants) in other.variants {
+            self.variants.entry(canonical).or_default().extend(variants);
+        }
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        self.num_tokens = self.tokens_map.len();
+        self.num_unfiltered_tokens = self.num_tokens + self.stopwords_map.len();
+    }
+}
+
+impl<'a> TokenStatistics<'a> {
+    /// Copies the tokens and members borrowed from the messages.
+    pub fn into_owned(self) -> TokenStatistics<'static> {
+        let owned_tokens = |map: HashMap<Token<'a>, usize>| map.into_iter().map(|(token, occurences)| (token.into_owned(), occurences)).collect();
+        TokenStatistics {
+            num_tokens: self.num_tokens,
+            num_unfiltered_tokens: self.num_unfiltered_tokens,
+            members_tokens_map: self.members_tokens_map.into_iter().map(|(member, map)| (member.into_owned(), owned_tokens(map))).collect(),
+            tokens_map: owned_tokens(self.tokens_map),
+            stopwords_map: owned_tokens(self.stopwords_map),
+            variants: self
+                .variants
+                .into_iter()
+                .map(|(canonical, variants)| (canonical.into_owned(), variants.into_iter().map(Token::into_owned).collect()))
+                .collect(),
+        }
+    }
+}
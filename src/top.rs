@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::member::Member;
+use crate::Token;
+
+/// The most used tokens overall and of every member.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TopStatistics<'a> {
+    /// Over every message, most used first.
+    #[serde(borrow)]
+    pub overall: Vec<Ranked<'a>>,
+    /// Over the messages of every member, most used first.
+    #[serde(borrow)]
+    pub members: HashMap<Member<'a>, Vec<Ranked<'a>>>,
+}
+
+/// A token and how often it was used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ranked<'a> {
+    /// Tokens used equally often share a rank and the following ranks are skipped: `1, 2, 2, 4`.
+    pub rank: usize,
+    /// The token itself.
+    #[serde(borrow)]
+    pub token: Token<'a>,
+    /// Its occurrences.
+    pub count: usize,
+    /// `count` over all token occurrences of the same list, overall or of the member.
+    pub share: f64,
+}
+
+impl<'a> TopStatistics<'a> {
+    /// Ranks the `n` most used tokens of each map.
+    pub fn new(n: usize, tokens_map: &HashMap<Token<'a>, usize>, members_tokens_map: &HashMap<Member<'a>, HashMap<Token<'a>, usize>>) -> Self {
+        Self {
+            overall: top(n, tokens_map),
+            members: members_tokens_map.iter().map(|(member, map)| (member.clone(), top(n, map))).collect(),
+        }
+    }
+    /// Copies the tokens and members borrowed from the messages.
+    pub fn into_owned(self) -> TopStatistics<'static> {
+        let owned = |ranked: Vec<Ranked<'a>>| ranked.into_iter().map(Ranked::into_owned).collect();
+        TopStatistics {
+            overall: owned(self.overall),
+            members: self.members.into_iter().map(|(member, ranked)| (member.into_owned(), owned(ranked))).collect(),
+        }
+    }
+}
+
+impl Ranked<'_> {
+    /// Copies the token borrowed from the messages.
+    pub fn into_owned(self) -> Ranked<'static> {
+        Ranked { rank: self.rank, token: self.token.into_owned(), count: self.count, share: self.share }
+    }
+}
+
+/// The `n` most used tokens of `map`, by descending count and then alphabetically, so ties are cut the same way on every run.
+fn top<'a>(n: usize, map: &HashMap<Token<'a>, usize>) -> Vec<Ranked<'a>> {
+    let mut entries: Vec<(&Token<'a>, usize)> = map.iter().map(|(token, &count)| (token, count)).collect();
+    let total: usize = entries.iter().map(|(_, count)| count).sum();
+    let order = |a: &(&Token, usize), b: &(&Token, usize)| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0));
+    if n < entries.len() {
+        entries.select_nth_unstable_by(n, order);
+        entries.truncate(n);
+    }
+    entries.sort_unstable_by(order);
+
+    let mut ranked: Vec<Ranked<'a>> = Vec::with_capacity(entries.len());
+    for (i, (token, count)) in entries.into_iter().enumerate() {
+        let rank = match ranked.last() {
+            Some(previous) if previous.count == count => previous.rank,
+            _ => i + 1,
+        };
+        ranked.push(Ranked { rank, token: token.clone(), count, share: count as f64 / total as f64 });
+    }
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranks(n: usize, counts: &[(&str, usize)]) -> Vec<(usize, String, usize)> {
+        let map = counts.iter().map(|&(token, count)| (Token::from(token), count)).collect();
+        top(n, &map).into_iter().map(|ranked| (ranked.rank, ranked.token.0.into_owned(), ranked.count)).collect()
+    }
+
+    fn ranked(expected: &[(usize, &str, usize)]) -> Vec<(usize, String, usize)> {
+        expected.iter().map(|&(rank, token, count)| (rank, token.to_string(), count)).collect()
+    }
+
+    #[test]
+    fn ties_share_a_rank() {
+        let counts = [("d", 1), ("b", 3), ("a", 5), ("c", 3), ("e", 1)];
+        assert_eq!(ranks(10, &counts), ranked(&[(1, "a", 5), (2, "b", 3), (2, "c", 3), (4, "d", 1), (4, "e", 1)]));
+    }
+
+    #[test]
+    fn truncates_ties_alphabetically() {
+        let counts = [("d", 1), ("b", 3), ("a", 5), ("c", 3), ("e", 1)];
+        assert_eq!(ranks(2, &counts), ranked(&[(1, "a", 5), (2, "b", 3)]));
+        assert_eq!(ranks(4, &counts), ranked(&[(1, "a", 5), (2, "b", 3), (2, "c", 3), (4, "d", 1)]));
+    }
+
+    #[test]
+    fn shares_are_of_every_occurrence() {
+        let map = [(Token::from("a"), 3), (Token::from("b"), 1)].into_iter().collect();
+        let shares: Vec<f64> = top(1, &map).into_iter().map(|ranked| ranked.share).collect();
+        assert_eq!(shares, [0.75]);
+    }
+}
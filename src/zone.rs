@@ -0,0 +1,137 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use chrono_tz::Tz;
+use serde::{Serialize, Serializer};
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::model::Message;
+
+/// The time zone message times are counted in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    /// `date` as written, in the local time of whoever exported the chat.
+    #[default]
+    Export,
+    /// An IANA zone such as `Europe/Kyiv`, daylight saving time included.
+    Named(Tz),
+    /// An offset from UTC such as `+02:00`, all year round.
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    /// When `message` was sent in this zone, or its `date` as written if it has no `date_unixtime`.
+    pub fn local(&self, message: &Message) -> NaiveDateTime {
+        let Some(utc) = message.date_unixtime.and_then(|seconds| DateTime::from_timestamp(seconds, 0)) else {
+            return message.date;
+        };
+        match self {
+            Zone::Export => message.date,
+            Zone::Named(zone) => utc.with_timezone(zone).naive_local(),
+            Zone::Fixed(offset) => utc.with_timezone(offset).naive_local(),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    /// An IANA name or an offset from UTC: `+02`, `-0530` or `+05:30`.
+    fn from_str(zone: &str) -> Result<Self, Self::Err> {
+        if let Ok(zone) = zone.parse::<Tz>() {
+            return Ok(Zone::Named(zone));
+        }
+        let invalid = || format!("`{zone}` is neither an IANA time zone nor an offset like +02:00");
+        let (sign, offset) = match zone.as_bytes().first() {
+            Some(b'+') => (1, &zone[1..]),
+            Some(b'-') => (-1, &zone[1..]),
+            _ => return Err(invalid()),
+        };
+        let (hours, minutes) = match (offset.len(), offset.split_once(':')) {
+            (5, Some((hours, minutes))) => (hours, minutes),
+            (4, None) => offset.split_at(2),
+            (2, None) => (offset, "00"),
+            _ => return Err(invalid()),
+        };
+        let digits = |part: &str| part.len() == 2 && part.bytes().all(|byte| byte.is_ascii_digit());
+        if !digits(hours) || !digits(minutes) {
+            return Err(invalid());
+        }
+        let (hours, minutes): (i32, i32) = (hours.parse().map_err(|_| invalid())?, minutes.parse().map_err(|_| invalid())?);
+        if minutes >= 60 {
+            return Err(invalid());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).map(Zone::Fixed).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Export => f.write_str("export"),
+            Zone::Named(zone) => zone.fmt(f),
+            Zone::Fixed(offset) => offset.fmt(f),
+        }
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(zone: &str) -> Option<i32> {
+        match zone.parse() {
+            Ok(Zone::Fixed(offset)) => Some(offset.local_minus_utc()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(offset("+02"), Some(2 * 3600));
+        assert_eq!(offset("+0530"), Some(5 * 3600 + 30 * 60));
+        assert_eq!(offset("-05:30"), Some(-(5 * 3600 + 30 * 60)));
+        assert_eq!(offset("+00:00"), Some(0));
+    }
+
+    #[test]
+    fn rejects_malformed_offsets() {
+        for zone in ["+-2", "+053", "+3", "+2:00", "+02:0", "+0260", "+24:00", "02:00", "+02:00:00", "+ 2", ""] {
+            assert!(zone.parse::<Zone>().is_err(), "{zone}");
+        }
+    }
+
+    #[test]
+    fn parses_iana_names() {
+        assert_eq!("Europe/Kyiv".parse(), Ok(Zone::Named(Tz::Europe__Kyiv)));
+        assert!("Mars/Base".parse::<Zone>().is_err());
+    }
+
+    fn at(unixtime: i64) -> Message<'static> {
+        Message { date: DateTime::from_timestamp(unixtime, 0).unwrap().naive_utc(), date_unixtime: Some(unixtime), ..Default::default() }
+    }
+
+    #[test]
+    fn follows_daylight_saving_time() {
+        let kyiv = Zone::Named(Tz::Europe__Kyiv);
+        let local = |unixtime| kyiv.local(&at(unixtime)).to_string();
+        // Clocks went from 03:00 to 04:00 on 26 March 2023, and from 04:00 back to 03:00 on 29 October.
+        assert_eq!(local(1679790600), "2023-03-26 02:30:00");
+        assert_eq!(local(1679794200), "2023-03-26 04:30:00");
+        assert_eq!(local(1698539400), "2023-10-29 03:30:00");
+        assert_eq!(local(1698543000), "2023-10-29 03:30:00");
+    }
+
+    #[test]
+    fn keeps_export_dates() {
+        let message = Message { date_unixtime: None, ..at(1679790600) };
+        assert_eq!(Zone::Fixed(FixedOffset::east_opt(3600).unwrap()).local(&message), message.date);
+        assert_eq!(Zone::Export.local(&at(1679790600)), at(1679790600).date);
+    }
+}